@@ -0,0 +1,100 @@
+use std::{
+    collections::VecDeque,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{PoisonError, RwLock},
+};
+
+use crate::image::Page;
+
+// decoded pages kept around the shown one so turning back and forth does not decode them again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CacheConfig {
+    // pages decoded ahead of time in each direction of the shown one.
+    pub prefetch: usize,
+    // bytes of decoded pixels the cache can hold.
+    pub max_bytes: u64,
+}
+
+impl CacheConfig {
+    const DEFAULT: Self = Self {
+        prefetch: 2,
+        max_bytes: 256 * 1024 * 1024,
+    };
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+static CONFIG: RwLock<CacheConfig> = RwLock::new(CacheConfig::DEFAULT);
+
+// replace the config pages are cached with from now on.
+pub fn set_config(config: CacheConfig) {
+    *CONFIG.write().unwrap_or_else(PoisonError::into_inner) = config;
+}
+
+pub(crate) fn config() -> CacheConfig {
+    *CONFIG.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+// page is known by the book it's in and it's position there.
+pub(crate) fn key(book: u64, position: &[usize]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (book, position).hash(&mut hasher);
+    hasher.finish()
+}
+
+// least recently used pages are dropped first when the cache goes over it's budget.
+#[derive(Default)]
+pub(crate) struct PageCache {
+    // least recently used first.
+    pages: VecDeque<(u64, Page)>,
+    bytes: u64,
+}
+
+impl PageCache {
+    pub(crate) fn contains(&self, key: u64) -> bool {
+        self.pages.iter().any(|(k, _)| *k == key)
+    }
+
+    pub(crate) fn get(&mut self, key: u64) -> Option<Page> {
+        let idx = self.pages.iter().position(|(k, _)| *k == key)?;
+        let entry = self.pages.remove(idx)?;
+        let page = entry.1.clone();
+        self.pages.push_back(entry);
+        Some(page)
+    }
+
+    pub(crate) fn insert(&mut self, key: u64, page: &Page) {
+        let max_bytes = config().max_bytes;
+        let size = page_size(page);
+        if size > max_bytes || self.contains(key) {
+            return;
+        }
+
+        self.bytes += size;
+        self.pages.push_back((key, page.clone()));
+        while self.bytes > max_bytes
+            && let Some((_, page)) = self.pages.pop_front()
+        {
+            self.bytes -= page_size(&page);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.pages.clear();
+        self.bytes = 0;
+    }
+}
+
+fn page_size(page: &Page) -> u64 {
+    match page {
+        Page::Still(image) => image.as_raw().len() as u64,
+        Page::Animated(frames) => frames
+            .iter()
+            .map(|(image, _)| image.as_raw().len() as u64)
+            .sum(),
+    }
+}
@@ -0,0 +1,58 @@
+use std::{
+    io::{self, Read},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+// work of a request running on worker thread is cancelled once a newer request makes it
+// pointless. a request is cancelled by an open of another file and prefetching by any request.
+// long reads check it between chunks so the newer request does not wait for them.
+static LATEST: AtomicU64 = AtomicU64::new(0);
+static LATEST_OPEN: AtomicU64 = AtomicU64::new(0);
+// id of request being run or the last one run while prefetching.
+static RUNNING: AtomicU64 = AtomicU64::new(0);
+static PREFETCHING: AtomicBool = AtomicBool::new(false);
+
+// tell request with given id is sent. called after it's queued.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn sent(id: u64, open: bool) {
+    LATEST.fetch_max(id, Ordering::Relaxed);
+    if open {
+        LATEST_OPEN.fetch_max(id, Ordering::Relaxed);
+    }
+}
+
+// tell request with given id starts running or it's followed by prefetching.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn running(id: u64, prefetch: bool) {
+    RUNNING.store(id, Ordering::Relaxed);
+    PREFETCHING.store(prefetch, Ordering::Relaxed);
+}
+
+pub(crate) fn is_cancelled() -> bool {
+    let latest = if PREFETCHING.load(Ordering::Relaxed) {
+        &LATEST
+    } else {
+        &LATEST_OPEN
+    };
+    latest.load(Ordering::Relaxed) > RUNNING.load(Ordering::Relaxed)
+}
+
+pub(crate) fn check() -> io::Result<()> {
+    if is_cancelled() {
+        return Err(io::Error::other("loading is cancelled"));
+    }
+    Ok(())
+}
+
+// reader that fails once current work is cancelled.
+pub(crate) struct Reader<R>(pub(crate) R);
+
+impl<R> Read for Reader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        check()?;
+        self.0.read(buf)
+    }
+}
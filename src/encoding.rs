@@ -0,0 +1,111 @@
+use encoding_rs::{BIG5, EUC_KR, Encoding, GBK, SHIFT_JIS};
+
+// legacy encoding of entry names in archives made without unicode support.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NameEncoding {
+    // default of zip spec and what most western archivers write.
+    Cp437,
+    ShiftJis,
+    Gbk,
+    Big5,
+    EucKr,
+}
+
+impl NameEncoding {
+    pub(crate) const ALL: [Self; 5] = [
+        Self::Cp437,
+        Self::ShiftJis,
+        Self::Gbk,
+        Self::Big5,
+        Self::EucKr,
+    ];
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            Self::Cp437 => "CP437",
+            Self::ShiftJis => "Shift-JIS",
+            Self::Gbk => "GBK",
+            Self::Big5 => "Big5",
+            Self::EucKr => "EUC-KR",
+        }
+    }
+
+    // guess encoding from raw names. CP437 is picked when none of the CJK encodings fit.
+    pub(crate) fn detect<'a>(names: impl Iterator<Item = &'a [u8]>) -> Self {
+        let mut detector = chardetng::EncodingDetector::new();
+        for name in names {
+            detector.feed(name, false);
+            detector.feed(b"\n", false);
+        }
+        detector.feed(&[], true);
+
+        let encoding = detector.guess(None, false);
+        Self::ALL
+            .into_iter()
+            .find(|e| e.encoding() == Some(encoding))
+            .unwrap_or(Self::Cp437)
+    }
+
+    // CP437 is not a web encoding and has no encoding_rs counterpart. zip decodes it by itself.
+    pub(crate) fn encoding(&self) -> Option<&'static Encoding> {
+        match self {
+            Self::Cp437 => None,
+            Self::ShiftJis => Some(SHIFT_JIS),
+            Self::Gbk => Some(GBK),
+            Self::Big5 => Some(BIG5),
+            Self::EucKr => Some(EUC_KR),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(names: &[&str], encoding: &'static Encoding) -> NameEncoding {
+        let names = names
+            .iter()
+            .map(|name| encoding.encode(name).0.into_owned())
+            .collect::<Vec<_>>();
+        NameEncoding::detect(names.iter().map(Vec::as_slice))
+    }
+
+    #[test]
+    fn cjk_names_are_detected() {
+        let japanese = [
+            "第一話/表紙.jpg",
+            "第一話/本文です.jpg",
+            "あとがき/おまけ漫画.png",
+        ];
+        assert!(detect(&japanese, SHIFT_JIS) == NameEncoding::ShiftJis);
+
+        let chinese = [
+            "第一话/封面.jpg",
+            "第一话/这是正文.jpg",
+            "后记/番外漫画.png",
+        ];
+        assert!(detect(&chinese, GBK) == NameEncoding::Gbk);
+
+        let traditional = [
+            "第一話/封面.jpg",
+            "第一話/這是正文.jpg",
+            "後記/番外漫畫.png",
+        ];
+        assert!(detect(&traditional, BIG5) == NameEncoding::Big5);
+
+        let korean = [
+            "제일화/표지.jpg",
+            "제일화/본문입니다.jpg",
+            "후기/보너스만화.png",
+        ];
+        assert!(detect(&korean, EUC_KR) == NameEncoding::EucKr);
+    }
+
+    #[test]
+    fn other_names_fall_back_to_cp437() {
+        assert!(
+            detect(&["chapter 1/001.jpg", "chapter 1/002.jpg"], SHIFT_JIS) == NameEncoding::Cp437
+        );
+        assert!(NameEncoding::detect(std::iter::empty()) == NameEncoding::Cp437);
+    }
+}
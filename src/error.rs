@@ -5,7 +5,21 @@ use image::ImageError;
 pub(crate) enum Error {
     Io(io::Error),
     Zip(zip::result::ZipError),
+    #[cfg(not(target_arch = "wasm32"))]
+    Rar(unrar::error::UnrarError),
+    SevenZ(sevenz_rust2::Error),
+    Pdf(lopdf::Error),
+    Tiff(tiff::TiffError),
+    Xml(roxmltree::Error),
     Image(ImageError),
+    // input is valid but uses a feature the viewer can not handle.
+    Unsupported(String),
+    // encrypted entry is hit and no password is given.
+    PasswordRequired,
+    // given password can not decrypt the entry. (it's not corruption)
+    WrongPassword,
+    // input goes over one of the safety limits. (see limit::Limits)
+    LimitExceeded(String),
 }
 
 impl fmt::Debug for Error {
@@ -14,6 +28,16 @@ impl fmt::Debug for Error {
             Self::Io(ref e) => e.fmt(f),
             Self::Image(ref e) => e.fmt(f),
             Self::Zip(ref e) => e.fmt(f),
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Rar(ref e) => e.fmt(f),
+            Self::SevenZ(ref e) => e.fmt(f),
+            Self::Pdf(ref e) => e.fmt(f),
+            Self::Tiff(ref e) => e.fmt(f),
+            Self::Xml(ref e) => e.fmt(f),
+            Self::Unsupported(ref e) => f.write_str(e),
+            Self::PasswordRequired => f.write_str("password required"),
+            Self::WrongPassword => f.write_str("wrong password"),
+            Self::LimitExceeded(ref e) => f.write_str(e),
         }
     }
 }
@@ -24,19 +48,76 @@ impl fmt::Display for Error {
             Self::Io(ref e) => e.fmt(f),
             Self::Image(ref e) => e.fmt(f),
             Self::Zip(ref e) => e.fmt(f),
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Rar(ref e) => e.fmt(f),
+            Self::SevenZ(ref e) => e.fmt(f),
+            Self::Pdf(ref e) => e.fmt(f),
+            Self::Tiff(ref e) => e.fmt(f),
+            Self::Xml(ref e) => e.fmt(f),
+            Self::Unsupported(ref e) => f.write_str(e),
+            Self::PasswordRequired => f.write_str("password required"),
+            Self::WrongPassword => f.write_str("wrong password"),
+            Self::LimitExceeded(ref e) => f.write_str(e),
         }
     }
 }
 
 impl From<ImageError> for Error {
     fn from(e: ImageError) -> Self {
-        Self::Image(e)
+        match e {
+            ImageError::Limits(e) => Self::LimitExceeded(format!("image decoding: {e}")),
+            e => Self::Image(e),
+        }
     }
 }
 
 impl From<zip::result::ZipError> for Error {
     fn from(e: zip::result::ZipError) -> Self {
-        Self::Zip(e)
+        use zip::result::ZipError;
+
+        match e {
+            ZipError::UnsupportedArchive(msg) if msg == ZipError::PASSWORD_REQUIRED => {
+                Self::PasswordRequired
+            }
+            ZipError::InvalidPassword => Self::WrongPassword,
+            e => Self::Zip(e),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<unrar::error::UnrarError> for Error {
+    fn from(e: unrar::error::UnrarError) -> Self {
+        Self::Rar(e)
+    }
+}
+
+impl From<sevenz_rust2::Error> for Error {
+    fn from(e: sevenz_rust2::Error) -> Self {
+        Self::SevenZ(e)
+    }
+}
+
+impl From<lopdf::Error> for Error {
+    fn from(e: lopdf::Error) -> Self {
+        Self::Pdf(e)
+    }
+}
+
+impl From<tiff::TiffError> for Error {
+    fn from(e: tiff::TiffError) -> Self {
+        match e {
+            e @ tiff::TiffError::LimitsExceeded => {
+                Self::LimitExceeded(format!("tiff decoding: {e}"))
+            }
+            e => Self::Tiff(e),
+        }
+    }
+}
+
+impl From<roxmltree::Error> for Error {
+    fn from(e: roxmltree::Error) -> Self {
+        Self::Xml(e)
     }
 }
 
@@ -1,20 +1,53 @@
 use std::{
+    cell::RefCell,
+    collections::HashMap,
     io::{Read, Seek},
     path::PathBuf,
+    rc::Rc,
+    time::Duration,
 };
 
-use eframe::egui::ColorImage;
 use zip::ZipArchive;
 
-use crate::error::Error;
+use crate::{cache, encoding::NameEncoding, error::Error, image::Page, limit, sort::SortMode};
+
+use format::Format;
+
+mod bytes;
+mod epub;
+mod format;
+mod pdf;
+#[cfg(not(target_arch = "wasm32"))]
+mod rar;
+mod raster;
+mod recover;
+mod sevenz;
+#[cfg(not(target_arch = "wasm32"))]
+mod split;
+mod tarball;
+mod tif;
+mod ugoira;
 
 #[allow(dead_code)]
+#[derive(Clone, Copy)]
 enum Direction {
     First,
     Last,
     Next,
     Prev,
     Offset(usize),
+    // first page of chapter with given index.
+    Chapter(usize),
+}
+
+// bytes of every frame of an animated page and how long each is shown.
+type Frames = Vec<(Vec<u8>, Duration)>;
+
+// page read from file and not decoded yet. decoding is left to the caller so a page turned past
+// before it's shown is never decoded.
+pub(crate) enum RawPage {
+    Image(Vec<u8>),
+    Frames(Frames),
 }
 
 trait File {
@@ -24,6 +57,121 @@ trait File {
     fn is_eof(&self) -> bool;
 
     fn read(&mut self, buf: &mut Vec<u8>, direction: Direction) -> Result<(), Error>;
+
+    // read the page distance away from current one (backward when negative) without moving to
+    // it. return Ok(false) when there is no such page in current book. pages inside another
+    // book are never peeked. (pages are not prefetched in browser)
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    fn peek(&mut self, _: &mut Vec<u8>, _: isize) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    // push the position of page distance away from current one (the page peek reads) as indices
+    // from this book inward. return false when there is no such page.
+    fn locate(&self, _: isize, _: &mut Vec<usize>) -> bool {
+        false
+    }
+
+    // reorder entries with given sort mode. index is reset to the head.
+    fn sort(&mut self, sort: SortMode);
+
+    // pages are meant to be read from right to left.
+    fn is_rtl(&self) -> bool {
+        false
+    }
+
+    // encoding of legacy entry names. None when all names are unicode.
+    fn name_encoding(&self) -> Option<NameEncoding> {
+        None
+    }
+
+    // decode legacy entry names with given encoding and read the first page again.
+    fn set_name_encoding(&mut self, _: &mut Vec<u8>, _: NameEncoding) -> Result<(), Error> {
+        Ok(())
+    }
+
+    // decrypt encrypted entries with given password from now on.
+    fn set_password(&mut self, _: &[u8]) {}
+
+    // problem of the file or current page worth telling the user. (a damaged archive)
+    fn notice(&self) -> Option<String> {
+        None
+    }
+
+    // chapters as (name, index of it's first page). empty when the book has no chapter.
+    fn chapters(&self) -> &[(Box<str>, usize)] {
+        &[]
+    }
+
+    // index of the chapter current page belongs to.
+    fn chapter(&self) -> Option<usize> {
+        None
+    }
+
+    // frames of current page when it's an animation stored as separate images. (pixiv ugoira)
+    // None when current page is a single image.
+    fn read_frames(&mut self) -> Result<Option<Frames>, Error> {
+        Ok(None)
+    }
+}
+
+// group pages sorted by name into chapters. a folder and a nested archive are chapters of their
+// own. pages are reordered so files of a folder come before it's sub folders, otherwise a
+// folder would be split apart by it's sub folders. a book with less than two chapters has no
+// chapter.
+fn group_chapters<T>(pages: &mut [T], name: impl Fn(&T) -> &str) -> Box<[(Box<str>, usize)]> {
+    // position every folder first shows up at. it keeps the sorted order of folders and files
+    // at the same level.
+    let mut first = HashMap::<Box<str>, usize>::new();
+    for (idx, page) in pages.iter().enumerate() {
+        let name = name(page);
+        for (end, _) in name.match_indices('/').chain([(name.len(), "")]) {
+            first.entry(Box::from(&name[..end])).or_insert(idx);
+        }
+    }
+    pages.sort_by_cached_key(|page| {
+        let name = name(page);
+        let ends = name.match_indices('/').map(|(end, _)| end);
+        ends.map(|end| (true, first[&name[..end]]))
+            .chain([(false, first[name])])
+            .collect::<Vec<_>>()
+    });
+
+    let mut chapters = Vec::<(Box<str>, usize)>::new();
+    for (idx, page) in pages.iter().enumerate() {
+        let name = name(page);
+        let key = if is_archive_name(name) {
+            name
+        } else {
+            folder(name)
+        };
+        if chapters.last().is_none_or(|(last, _)| **last != *key) {
+            chapters.push((Box::from(key), idx));
+        }
+    }
+
+    if chapters.len() < 2 {
+        Box::default()
+    } else {
+        chapters.into_boxed_slice()
+    }
+}
+
+// index distance away from idx. None when it's out of len pages.
+fn peek_index(idx: usize, len: usize, distance: isize) -> Option<usize> {
+    idx.checked_add_signed(distance).filter(|idx| *idx < len)
+}
+
+fn chapter_of(chapters: &[(Box<str>, usize)], idx: usize) -> Option<usize> {
+    chapters.iter().rposition(|(_, start)| *start <= idx)
+}
+
+// folder an entry inside archive belongs to. entries at root share "/".
+fn folder(name: &str) -> &str {
+    match name.rsplit_once('/') {
+        Some((dir, _)) if !dir.is_empty() => dir,
+        _ => "/",
+    }
 }
 
 struct NoFile;
@@ -40,141 +188,548 @@ impl File for NoFile {
     fn read(&mut self, _: &mut Vec<u8>, _: Direction) -> Result<(), Error> {
         Ok(())
     }
+
+    fn sort(&mut self, _: SortMode) {}
 }
 
 struct ZipFile<R> {
     idx: usize,
-    ordered_names: Box<[Box<str>]>,
+    // name of page or nested archive entry and it's index inside archive.
+    ordered_names: Box<[(Box<str>, usize)]>,
+    // entries that are not pages. (ComicInfo.xml, readme and such) kept for reading metadata.
+    extras: Box<[(Box<str>, usize)]>,
+    // folders and nested archives as chapters. (name, index of it's first entry)
+    chapters: Box<[(Box<str>, usize)]>,
+    // archive nested at current index. NoFile when current entry is an image.
+    child: Box<dyn File>,
+    // raw bytes of names indexed by their entry index inside archive. None for ascii names.
+    raw_names: Box<[Option<Box<[u8]>>]>,
+    // encoding of raw names that are not utf-8. None when there is no such name.
+    encoding: Option<NameEncoding>,
+    password: Option<Box<[u8]>>,
+    // frames and their delays when archive is a pixiv ugoira. it's shown as a single animated
+    // page with the first frame as it's still image.
+    ugoira: Option<Box<[(usize, Duration)]>>,
+    sort: SortMode,
     file: ZipArchive<R>,
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-impl TryFrom<&PathBuf> for ZipFile<std::fs::File> {
-    type Error = Error;
-
-    fn try_from(path: &PathBuf) -> Result<Self, Self::Error> {
-        let file = std::fs::File::open(path)?;
-        let file = ZipArchive::new(file)?;
-        let mut ordered_names = file
-            .file_names()
-            .map(Box::from)
-            .collect::<Box<[Box<str>]>>();
-        ordered_names.sort();
-        Ok(Self {
-            idx: 0,
-            ordered_names,
-            file,
-        })
-    }
-}
-
 impl<R> ZipFile<R>
 where
     R: Read + Seek,
 {
-    // a loop read auto advance the zip file index to skip nested folders inside zip file.
-    // TODO: properly handle nested folders in zip.
-    fn _read<F, F1>(
-        &mut self,
-        buf: &mut Vec<u8>,
-        mut condition: F,
-        mut on_continue: F1,
-    ) -> Result<(), Error>
-    where
-        F: FnMut(&mut Self) -> bool,
-        F1: FnMut(&mut Self),
-    {
-        while condition(self) {
-            let n = self.read_by_index(buf)?;
+    fn new(mut file: ZipArchive<R>, sort: SortMode) -> Self {
+        // zip decodes names without utf-8 flag as CP437. names from archivers that write local
+        // code page or utf-8 without the flag must be decoded from their raw bytes again.
+        let raw_names = (0..file.len())
+            .map(|idx| {
+                file.name_for_index(idx)
+                    .is_some_and(|name| !name.is_ascii())
+                    .then(|| Some(Box::from(file.by_index_raw(idx).ok()?.name_raw())))
+                    .flatten()
+            })
+            .collect::<Box<[_]>>();
+
+        let mut legacy = raw_names
+            .iter()
+            .flatten()
+            .map(|raw| &**raw)
+            .filter(|raw| std::str::from_utf8(raw).is_err())
+            .peekable();
+        let encoding = legacy
+            .peek()
+            .is_some()
+            .then(|| NameEncoding::detect(legacy));
+
+        let mut this = Self {
+            idx: 0,
+            ordered_names: Box::default(),
+            extras: Box::default(),
+            chapters: Box::default(),
+            child: Box::new(NoFile),
+            raw_names,
+            encoding,
+            password: None,
+            ugoira: None,
+            sort,
+            file,
+        };
+        this.index_names();
+        this
+    }
 
-            if n > 0 {
-                return Ok(());
-            }
+    // decode entry names and split them into pages and extras.
+    fn index_names(&mut self) {
+        let (ordered_names, extras) = (0..self.file.len())
+            .filter_map(|idx| Some((self.name_for_index(idx)?, idx)))
+            .filter(|(name, _)| !name.ends_with('/'))
+            .partition::<Vec<_>, _>(|(name, _)| is_page_name(name) || is_archive_name(name));
+
+        self.ordered_names = ordered_names.into_boxed_slice();
+        self.extras = extras.into_boxed_slice();
+
+        self.ugoira = self.ugoira_frames();
+        if let Some(&(first, _)) = self.ugoira.as_deref().and_then(|frames| frames.first()) {
+            self.ordered_names = self
+                .ordered_names
+                .iter()
+                .filter(|(_, idx)| *idx == first)
+                .cloned()
+                .collect();
+        }
+
+        self.sort(self.sort);
+    }
+
+    // frames listed by ugoira metadata. None when there is no metadata or a frame it lists is
+    // missing.
+    fn ugoira_frames(&mut self) -> Option<Box<[(usize, Duration)]>> {
+        let mut buf = Vec::new();
+        if !self.read_extra(ugoira::METADATA_NAME, &mut buf).ok()? {
+            return None;
+        }
+
+        ugoira::parse_frames(&buf)?
+            .into_iter()
+            .map(|(file, delay)| {
+                let (_, idx) = self.ordered_names.iter().find(|(name, _)| {
+                    name.strip_suffix(file.as_str())
+                        .is_some_and(|folder| folder.is_empty() || folder.ends_with('/'))
+                })?;
+                Some((*idx, Duration::from_millis(delay as u64)))
+            })
+            .collect()
+    }
+
+    fn name_for_index(&self, idx: usize) -> Option<Box<str>> {
+        let name = self.file.name_for_index(idx)?;
+
+        let Some(raw) = self.raw_names.get(idx).and_then(Option::as_deref) else {
+            return Some(Box::from(name));
+        };
+
+        let name = match std::str::from_utf8(raw) {
+            Ok(name) => Box::from(name),
+            Err(_) => match self.encoding.and_then(|e| e.encoding()) {
+                Some(encoding) => Box::from(encoding.decode_without_bom_handling(raw).0),
+                None => Box::from(name),
+            },
+        };
+
+        Some(name)
+    }
+
+    fn _is_eof(&self) -> bool {
+        self.idx == self.ordered_names.len().saturating_sub(1)
+    }
+
+    fn _is_head(&self) -> bool {
+        self.idx == 0
+    }
 
-            on_continue(self);
+    // read entry at current index. nested archive is opened as child and read from given
+    // direction.
+    fn read_by_index(&mut self, buf: &mut Vec<u8>, direction: Direction) -> Result<(), Error> {
+        self.child = Box::new(NoFile);
+
+        let Some((name, idx)) = self.ordered_names.get(self.idx) else {
+            return Ok(());
+        };
+        let (is_archive, idx) = (is_archive_name(name), *idx);
+        let password = self.password.as_deref();
+
+        if is_archive {
+            let mut bytes = Vec::new();
+            read_zip_entry(&mut self.file, idx, password, &mut bytes)?;
+            self.child = open_buf(bytes, self.sort)?;
+            if let Some(password) = self.password.as_deref() {
+                self.child.set_password(password);
+            }
+            return self.child.read(buf, direction);
         }
 
+        let start = buf.len();
+        read_zip_entry(&mut self.file, idx, password, buf)?;
+
+        // tiff of more than one page is a book nested in the archive.
+        let bytes = &buf[start..];
+        if Format::sniff(bytes) == Some(Format::Tiff)
+            && tif::is_multi_page(std::io::Cursor::new(bytes))
+        {
+            let bytes = buf.split_off(start);
+            self.child = open_buf(bytes, self.sort)?;
+            return self.child.read(buf, direction);
+        }
         Ok(())
     }
 
-    // return Ok(n) when current index is a file and filled given buf with n bytes.
-    // (include 0 bytes).
-    fn read_by_index(&mut self, buf: &mut Vec<u8>) -> Result<usize, Error> {
-        let name = &*self.ordered_names[self.idx];
-        let mut file = self.file.by_name(name)?;
+    // current entry is a book of it's own. (nested archive or tiff of many pages)
+    fn is_nested(&self) -> bool {
+        self.ordered_names
+            .get(self.idx)
+            .is_some_and(|(name, _)| is_archive_name(name))
+            || self.child.locate(0, &mut Vec::new())
+    }
 
-        let n = if file.is_file() {
-            buf.reserve(file.size() as usize);
-            file.read_to_end(buf)?
-        } else {
-            0
+    // read non page entry with given name. name is matched case-insensitive.
+    // return Ok(false) when there is no such entry.
+    fn read_extra(&mut self, name: &str, buf: &mut Vec<u8>) -> Result<bool, Error> {
+        let Some((_, idx)) = self
+            .extras
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        else {
+            return Ok(false);
         };
+        read_zip_entry(&mut self.file, *idx, self.password.as_deref(), buf)?;
+        Ok(true)
+    }
+}
 
-        Ok(n)
+// read zip entry into buf and decrypt it with password when there is one. entry not encrypted
+// ignores the password. zstd entry is decoded here when zip is built without it. (wasm)
+fn read_zip_entry<R>(
+    file: &mut ZipArchive<R>,
+    idx: usize,
+    password: Option<&[u8]>,
+    buf: &mut Vec<u8>,
+) -> Result<(), Error>
+where
+    R: Read + Seek,
+{
+    use zip::{CompressionMethod, result::ZipError};
+
+    let res = match password {
+        Some(password) => file.by_index_decrypt(idx, password),
+        None => file.by_index(idx),
+    };
+
+    let limits = limit::limits();
+    let e = match res.map(|entry| read_entry_limited(&limits, entry, buf)) {
+        Ok(res) => return res,
+        Err(e) => e,
+    };
+
+    match e {
+        ZipError::UnsupportedArchive(msg) if msg != ZipError::PASSWORD_REQUIRED => {
+            let entry = file.by_index_raw(idx)?;
+            let method = entry.compression();
+            if method == CompressionMethod::ZSTD && !entry.encrypted() {
+                let name = String::from(entry.name());
+                limits.check_entry(&name, entry.size(), entry.compressed_size())?;
+                buf.reserve(entry.size() as usize);
+                let decoder = ruzstd::decoding::StreamingDecoder::new(entry)
+                    .map_err(std::io::Error::other)?;
+                return limits.read_entry(&name, decoder, buf);
+            }
+            #[allow(deprecated)]
+            let method = method_name(method.to_u16());
+            Err(Error::Unsupported(format!(
+                "zip entry {} is compressed with unsupported method {method}",
+                entry.name(),
+            )))
+        }
+        e => Err(e.into()),
     }
 }
 
+fn read_entry_limited<R>(
+    limits: &limit::Limits,
+    entry: zip::read::ZipFile<'_, R>,
+    buf: &mut Vec<u8>,
+) -> Result<(), Error>
+where
+    R: Read,
+{
+    let name = String::from(entry.name());
+    limits.check_entry(&name, entry.size(), entry.compressed_size())?;
+    buf.reserve(entry.size() as usize);
+    limits.read_entry(&name, entry, buf)
+}
+
+fn method_name(id: u16) -> String {
+    let name = match id {
+        1 => "shrink",
+        2..=5 => "reduce",
+        6 => "implode",
+        8 => "deflate",
+        9 => "deflate64",
+        10 => "pkware implode",
+        12 => "bzip2",
+        14 => "lzma",
+        18 => "ibm terse",
+        19 => "ibm lz77",
+        20 | 93 => "zstd",
+        94 => "mp3",
+        95 => "xz",
+        96 => "jpeg",
+        97 => "wavpack",
+        98 => "ppmd",
+        99 => "aes",
+        _ => return format!("#{id}"),
+    };
+    name.into()
+}
+
 impl<R> File for ZipFile<R>
 where
     R: Read + Seek,
 {
     fn is_head(&self) -> bool {
-        self.idx == 0
+        self._is_head() && self.child.is_head()
     }
 
     fn is_eof(&self) -> bool {
-        self.idx == self.ordered_names.len().saturating_sub(1)
+        self._is_eof() && self.child.is_eof()
     }
 
-    fn read(&mut self, buf: &mut Vec<u8>, direction: Direction) -> Result<(), Error> {
-        match direction {
-            Direction::Next => self._read(
-                buf,
-                |this| {
-                    let cond = !this.is_eof();
-                    if cond {
-                        this.idx += 1;
-                    }
-                    cond
-                },
-                |_| {},
-            ),
-            Direction::First => self._read(
-                buf,
-                |this| !this.is_eof(),
-                |this| {
-                    this.idx += 1;
-                },
-            ),
-            Direction::Prev => self._read(
-                buf,
-                |this| {
-                    let cond = this.idx != 0;
-
-                    if cond {
-                        this.idx -= 1;
-                    }
+    fn read(&mut self, buf: &mut Vec<u8>, mut direction: Direction) -> Result<(), Error> {
+        let idx = self.idx;
 
-                    cond
-                },
-                |_| {},
-            ),
+        match direction {
+            Direction::Next if !self.child.is_eof() => return self.child.read(buf, direction),
+            Direction::Prev if !self.child.is_head() => return self.child.read(buf, direction),
+            Direction::Next if self._is_eof() => return Ok(()),
+            Direction::Prev if self._is_head() => return Ok(()),
+            Direction::Next => {
+                self.idx += 1;
+                direction = Direction::First;
+            }
+            Direction::Prev => {
+                self.idx -= 1;
+                direction = Direction::Last;
+            }
+            Direction::First => self.idx = 0,
+            Direction::Last => self.idx = self.ordered_names.len().saturating_sub(1),
             Direction::Offset(idx) => {
-                assert!(idx < self.ordered_names.len());
-                self.idx = idx;
+                self.idx = idx.min(self.ordered_names.len().saturating_sub(1));
+                direction = Direction::First;
+            }
+            Direction::Chapter(n) => match self.chapters.get(n) {
+                Some((_, start)) => {
+                    self.idx = *start;
+                    direction = Direction::First;
+                }
+                None => return Ok(()),
+            },
+        }
 
-                let _ = self.read_by_index(buf)?;
+        // a read stopped by password leaves the position unchanged so it's retried with one.
+        // other errors move past the bad page like a page that is read.
+        let res = self.read_by_index(buf, direction);
+        if matches!(res, Err(Error::PasswordRequired | Error::WrongPassword)) {
+            self.idx = idx;
+            self.child = Box::new(NoFile);
+        }
+        res
+    }
 
-                Ok(())
-            }
-            Direction::Last => {
-                self.idx = self.ordered_names.len();
-                self.read(buf, Direction::Prev)
+    fn locate(&self, distance: isize, position: &mut Vec<usize>) -> bool {
+        if self.is_nested() {
+            position.push(self.idx);
+            return self.child.locate(distance, position);
+        }
+        match peek_index(self.idx, self.ordered_names.len(), distance) {
+            Some(at) if !is_archive_name(&self.ordered_names[at].0) => {
+                position.push(at);
+                true
             }
+            _ => false,
+        }
+    }
+
+    // pages of nested book are peeked by it. an entry next to an image page is only peeked when
+    // it's an image too.
+    fn peek(&mut self, buf: &mut Vec<u8>, distance: isize) -> Result<bool, Error> {
+        if self.is_nested() {
+            return self.child.peek(buf, distance);
+        }
+        let Some(at) = peek_index(self.idx, self.ordered_names.len(), distance) else {
+            return Ok(false);
+        };
+        let (name, idx) = &self.ordered_names[at];
+        if is_archive_name(name) {
+            return Ok(false);
+        }
+        let start = buf.len();
+        read_zip_entry(&mut self.file, *idx, self.password.as_deref(), buf)?;
+        let bytes = &buf[start..];
+        if Format::sniff(bytes) == Some(Format::Tiff)
+            && tif::is_multi_page(std::io::Cursor::new(bytes))
+        {
+            buf.truncate(start);
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn sort(&mut self, sort: SortMode) {
+        self.ordered_names
+            .sort_by(|(a, _), (b, _)| sort.cmp_name(a, b));
+        self.sort = sort;
+        self.idx = 0;
+        // nested archive is a chapter of it's own.
+        self.chapters = group_chapters(&mut self.ordered_names, |(name, _)| name);
+        self.child = Box::new(NoFile);
+    }
+
+    fn chapters(&self) -> &[(Box<str>, usize)] {
+        &self.chapters
+    }
+
+    fn chapter(&self) -> Option<usize> {
+        chapter_of(&self.chapters, self.idx)
+    }
+
+    fn is_rtl(&self) -> bool {
+        self.child.is_rtl()
+    }
+
+    fn set_password(&mut self, password: &[u8]) {
+        self.password = Some(Box::from(password));
+        self.child.set_password(password);
+    }
+
+    fn notice(&self) -> Option<String> {
+        self.child.notice()
+    }
+
+    fn read_frames(&mut self) -> Result<Option<Frames>, Error> {
+        let Some(frames) = self.ugoira.as_deref() else {
+            return self.child.read_frames();
+        };
+
+        frames
+            .iter()
+            .map(|(idx, delay)| {
+                let mut buf = Vec::new();
+                read_zip_entry(&mut self.file, *idx, self.password.as_deref(), &mut buf)?;
+                Ok((buf, *delay))
+            })
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+
+    // nested archive has it's own names. the inner most archive with legacy names is the one
+    // encoding applies to.
+    fn name_encoding(&self) -> Option<NameEncoding> {
+        self.child.name_encoding().or(self.encoding)
+    }
+
+    fn set_name_encoding(
+        &mut self,
+        buf: &mut Vec<u8>,
+        encoding: NameEncoding,
+    ) -> Result<(), Error> {
+        if self.child.name_encoding().is_some() {
+            self.child.set_name_encoding(buf, encoding)
+        } else if self.encoding.is_some() {
+            self.encoding = Some(encoding);
+            self.index_names();
+            self.read_by_index(buf, Direction::First)
+        } else {
+            Ok(())
         }
     }
 }
 
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "gif", "bmp", "avif", "jxl"];
+
+// entry inside archive is a page when it's an image or has no extension to tell otherwise.
+// hidden files and macOS resource forks (__MACOSX/._001.jpg) are never pages.
+fn is_page_name(name: &str) -> bool {
+    if name
+        .split('/')
+        .any(|c| c.starts_with('.') || c == "__MACOSX")
+    {
+        return false;
+    }
+
+    // tiff of more than one page is opened as a book when it's read.
+    match name_extension(name) {
+        Some(ext) => [IMAGE_EXTENSIONS, TIFF_EXTENSIONS]
+            .iter()
+            .any(|extensions| extensions.iter().any(|e| e.eq_ignore_ascii_case(ext))),
+        None => true,
+    }
+}
+
+// entry inside archive is an archive itself. (zip of volumes)
+fn is_archive_name(name: &str) -> bool {
+    let Some(ext) = name_extension(name) else {
+        return false;
+    };
+    let is_ext = |extensions: &[&str]| extensions.iter().any(|e| e.eq_ignore_ascii_case(ext));
+    [
+        ZIP_EXTENSIONS,
+        SEVENZ_EXTENSIONS,
+        PDF_EXTENSIONS,
+    ]
+    .into_iter()
+    .any(is_ext)
+        || is_tar_name(name)
+        // rar is read from disk which browser does not have. nested one is left as an extra.
+        || (cfg!(not(target_arch = "wasm32")) && is_ext(RAR_EXTENSIONS))
+}
+
+fn name_extension(name: &str) -> Option<&str> {
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    file_name.rsplit_once('.').map(|(_, ext)| ext)
+}
+
+// epub is a zip with a package document describing the reading order of it's pages.
+fn open_zip<R>(reader: R, sort: SortMode) -> Result<Box<dyn File>, Error>
+where
+    R: Read + Seek + 'static,
+{
+    // zip without a valid central directory (a partial download mostly) has it's pages
+    // recovered from local headers instead.
+    let shared = SharedReader(Rc::new(RefCell::new(reader)));
+    let file = match ZipArchive::new(SharedReader(shared.0.clone())) {
+        Ok(file) => file,
+        Err(e) => {
+            // failed archive is dropped with it's handle and the reader is owned here again.
+            let Ok(reader) = Rc::try_unwrap(shared.0) else {
+                return Err(e.into());
+            };
+            let mut reader = reader.into_inner();
+            reader.rewind()?;
+            return match recover::RecoveredZip::try_new(reader, sort)? {
+                Some(file) => Ok(Box::new(file) as _),
+                None => Err(e.into()),
+            };
+        }
+    };
+
+    if file.index_for_name(epub::CONTAINER).is_some() {
+        Ok(Box::new(epub::EpubFile::try_new(file)?) as _)
+    } else {
+        Ok(Box::new(ZipFile::new(file, sort)) as _)
+    }
+}
+
+// reader handed to zip while a handle of it is kept. zip does not give the reader back when it
+// fails to parse the archive and it's needed to recover pages from local headers then.
+struct SharedReader<R>(Rc<RefCell<R>>);
+
+impl<R> Read for SharedReader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().read(buf)
+    }
+}
+
+impl<R> Seek for SharedReader<R>
+where
+    R: Seek,
+{
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        self.0.borrow_mut().seek(pos)
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 use nest::ListFile;
 
@@ -188,33 +743,120 @@ mod nest {
         idx: usize,
         file: Box<[PathBuf]>,
         child: Box<dyn File>,
+        password: Option<Box<[u8]>>,
+        sort: SortMode,
     }
 
-    impl TryFrom<&PathBuf> for ListFile {
-        type Error = Error;
+    impl ListFile {
+        pub(super) fn try_new(path: &PathBuf, sort: SortMode) -> Result<Self, Error> {
+            Self::try_new_with(path, sort, true)
+        }
 
-        fn try_from(path: &PathBuf) -> Result<Self, Self::Error> {
-            let mut files = Vec::new();
-            visit_dirs(path, &mut |p| files.push(p))?;
+        // listing of files right inside given folder without walking into sub folders. (the
+        // folder of a loose image)
+        pub(super) fn try_new_flat(path: &PathBuf, sort: SortMode) -> Result<Self, Error> {
+            Self::try_new_with(path, sort, false)
+        }
 
-            files.sort();
+        fn try_new_with(path: &PathBuf, sort: SortMode, recursive: bool) -> Result<Self, Error> {
+            let mut files = Vec::new();
+            visit_dirs(path, recursive, &mut |p| {
+                if !is_junk(&p) {
+                    files.push(p)
+                }
+            })?;
 
-            Ok(ListFile {
+            let mut this = ListFile {
                 idx: 0,
                 file: files.into_boxed_slice(),
                 child: Box::new(NoFile),
-            })
+                password: None,
+                sort,
+            };
+            this.sort(sort);
+            Ok(this)
+        }
+
+        // index of given file in the listing.
+        pub(super) fn position(&self, path: &std::path::Path) -> Option<usize> {
+            self.file.iter().position(|p| p == path)
         }
-    }
 
-    impl ListFile {
         fn _is_eof(&self) -> bool {
             self.idx == self.file.len().saturating_sub(1)
         }
 
+        // read file at current index and move on past files that are not pages or books. files
+        // are skipped backward when reading from the last page.
+        fn read_skipping(
+            &mut self,
+            buf: &mut Vec<u8>,
+            mut direction: Direction,
+        ) -> Result<(), Error> {
+            let backward = matches!(direction, Direction::Last);
+            while !self.read_by_index(buf, direction)? {
+                self.child = Box::new(NoFile);
+                match backward {
+                    true if !self._is_head() => self.idx -= 1,
+                    false if !self._is_eof() => self.idx += 1,
+                    _ => break,
+                }
+                direction = if backward {
+                    Direction::Last
+                } else {
+                    Direction::First
+                };
+            }
+            Ok(())
+        }
+
         fn _is_head(&self) -> bool {
             self.idx == 0
         }
+
+        // read file at current index. return Ok(false) when it's neither a page nor a book and
+        // should be skipped.
+        fn read_by_index(
+            &mut self,
+            buf: &mut Vec<u8>,
+            direction: Direction,
+        ) -> Result<bool, Error> {
+            // book of the file read before is not kept around a loose image.
+            self.child = Box::new(NoFile);
+
+            let Some(path) = self.file.get(self.idx) else {
+                return Ok(true);
+            };
+
+            if !path.is_file() {
+                assert!(path.is_dir());
+
+                self.child = Box::new(ListFile::try_new(path, self.sort)?) as _;
+            } else {
+                let format = Format::detect(path)?;
+
+                if format == Some(Format::Image) {
+                    let mut file = fs::File::open(path)?;
+
+                    if let Ok(meta) = file.metadata() {
+                        buf.reserve(meta.len() as usize);
+                    }
+                    file.read_to_end(buf)?;
+                    return Ok(true);
+                }
+
+                match open_archive(path, format, self.sort)? {
+                    Some(file) => self.child = file,
+                    None => return Ok(false),
+                }
+            }
+
+            if let Some(password) = self.password.as_deref() {
+                self.child.set_password(password);
+            }
+            self.child.read(buf, direction)?;
+            Ok(true)
+        }
     }
 
     impl File for ListFile {
@@ -227,7 +869,10 @@ mod nest {
         }
 
         fn read(&mut self, buf: &mut Vec<u8>, mut direction: Direction) -> Result<(), Error> {
+            let idx = self.idx;
+
             match direction {
+                Direction::Chapter(_) => return self.child.read(buf, direction),
                 Direction::Next if !self.child.is_eof() => return self.child.read(buf, direction),
                 Direction::Prev if !self.child.is_head() => return self.child.read(buf, direction),
                 Direction::Next if self._is_eof() => return Ok(()),
@@ -251,47 +896,116 @@ mod nest {
                 }
             }
 
-            let path = &self.file[self.idx];
-
-            if !path.is_file() {
-                assert!(path.is_dir());
+            // a read stopped by password leaves the position unchanged so it's retried with one.
+            // other errors move past the bad page like a page that is read.
+            let res = self.read_skipping(buf, direction);
+            if matches!(res, Err(Error::PasswordRequired | Error::WrongPassword)) {
+                self.idx = idx;
+                self.child = Box::new(NoFile);
+            }
+            res
+        }
 
-                self.child = Box::new(ListFile::try_from(path)?) as _;
+        fn locate(&self, distance: isize, position: &mut Vec<usize>) -> bool {
+            match self.file.get(self.idx) {
+                Some(path) if is_image_file(path).unwrap_or(false) => {}
+                _ => {
+                    position.push(self.idx);
+                    return self.child.locate(distance, position);
+                }
+            }
+            match peek_index(self.idx, self.file.len(), distance) {
+                Some(at) if is_image_file(&self.file[at]).unwrap_or(false) => {
+                    position.push(at);
+                    true
+                }
+                _ => false,
+            }
+        }
 
-                return self.child.read(buf, direction);
+        // book opened at current index is peeked by it. a loose image only peeks the images
+        // next to it.
+        fn peek(&mut self, buf: &mut Vec<u8>, distance: isize) -> Result<bool, Error> {
+            match self.file.get(self.idx) {
+                Some(path) if is_image_file(path)? => {}
+                _ => return self.child.peek(buf, distance),
             }
+            let Some(at) = peek_index(self.idx, self.file.len(), distance) else {
+                return Ok(false);
+            };
+            let path = &self.file[at];
+            if !is_image_file(path)? {
+                return Ok(false);
+            }
+            buf.extend_from_slice(&fs::read(path)?);
+            Ok(true)
+        }
 
-            let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+        fn sort(&mut self, sort: SortMode) {
+            self.file.sort_by(|a, b| sort.cmp_path(a, b));
+            self.sort = sort;
+            self.idx = 0;
+            self.child = Box::new(NoFile);
+        }
 
-            match ext {
-                "jpg" | "jpeg" | "png" | "webp" => {
-                    let mut file = fs::File::open(path)?;
+        fn is_rtl(&self) -> bool {
+            self.child.is_rtl()
+        }
 
-                    if let Ok(meta) = file.metadata() {
-                        buf.reserve(meta.len() as usize);
-                    }
-                    file.read_to_end(buf)?;
-                    Ok(())
-                }
-                // treat all uncertain file extensions as zip file.
-                // zip archive would return a format error for all files that are not supported.
-                // TODO: add special error handling for determined non zip files.
-                _ => {
-                    self.child = Box::new(ZipFile::try_from(path)?) as _;
-                    self.child.read(buf, direction)
-                }
-            }
+        fn set_password(&mut self, password: &[u8]) {
+            self.password = Some(Box::from(password));
+            self.child.set_password(password);
+        }
+
+        fn notice(&self) -> Option<String> {
+            self.child.notice()
+        }
+
+        fn read_frames(&mut self) -> Result<Option<Frames>, Error> {
+            self.child.read_frames()
+        }
+
+        fn name_encoding(&self) -> Option<NameEncoding> {
+            self.child.name_encoding()
+        }
+
+        fn chapters(&self) -> &[(Box<str>, usize)] {
+            self.child.chapters()
+        }
+
+        fn chapter(&self) -> Option<usize> {
+            self.child.chapter()
+        }
+
+        fn set_name_encoding(
+            &mut self,
+            buf: &mut Vec<u8>,
+            encoding: NameEncoding,
+        ) -> Result<(), Error> {
+            self.child.set_name_encoding(buf, encoding)
         }
     }
 
+    fn is_image_file(path: &std::path::Path) -> Result<bool, Error> {
+        Ok(path.is_file() && Format::detect(path)? == Some(Format::Image))
+    }
+
+    // call cb with every file inside dir. sub folders are walked into when recursive and left
+    // out otherwise.
     #[inline(never)]
-    fn visit_dirs(dir: &PathBuf, cb: &mut dyn FnMut(PathBuf)) -> Result<(), Error> {
+    fn visit_dirs(
+        dir: &PathBuf,
+        recursive: bool,
+        cb: &mut dyn FnMut(PathBuf),
+    ) -> Result<(), Error> {
         if dir.is_dir() {
             for entry in fs::read_dir(dir)? {
                 let entry = entry?;
                 let path = entry.path();
                 if path.is_dir() {
-                    visit_dirs(&path, cb)?;
+                    if recursive {
+                        visit_dirs(&path, recursive, cb)?;
+                    }
                 } else {
                     cb(path);
                 }
@@ -306,8 +1020,17 @@ pub(crate) struct FileObj {
     res: [u32; 2],
     file: Box<dyn File>,
     buf: Vec<u8>,
+    sort: SortMode,
+    // read stopped by missing or wrong password. it's retried when a password is given.
+    pending: Option<Direction>,
+    // changes whenever pages can move to another position. (new file, sort, name encoding)
+    book: u64,
+    // passwords remembered for the session. key is the path of opened file.
+    passwords: HashMap<PathBuf, Box<[u8]>>,
     #[allow(dead_code)]
     directory_hint: PathBuf,
+    #[cfg(not(target_arch = "wasm32"))]
+    siblings: Siblings,
 }
 
 impl FileObj {
@@ -316,85 +1039,255 @@ impl FileObj {
             res,
             file: Box::new(NoFile),
             buf: Vec::new(),
+            sort: SortMode::default(),
+            pending: None,
+            book: 0,
+            passwords: HashMap::new(),
             directory_hint: PathBuf::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            siblings: Siblings::default(),
         }
     }
 
-    pub(crate) fn try_next(&mut self) -> Result<Option<ColorImage>, Error> {
+    pub(crate) fn sort_mode(&self) -> SortMode {
+        self.sort
+    }
+
+    // change sort mode and start over from the first page of current file.
+    pub(crate) fn try_sort(&mut self, sort: SortMode) -> Result<Option<RawPage>, Error> {
+        self.sort = sort;
+        self.book += 1;
+        self.file.sort(sort);
+        self.try_read(Direction::First)
+    }
+
+    pub(crate) fn name_encoding(&self) -> Option<NameEncoding> {
+        self.file.name_encoding()
+    }
+
+    // decode entry names of current archive with given encoding and start over from it's first
+    // page.
+    pub(crate) fn try_set_name_encoding(
+        &mut self,
+        encoding: NameEncoding,
+    ) -> Result<Option<RawPage>, Error> {
+        self.book += 1;
+        let res = self
+            .file
+            .set_name_encoding(&mut self.buf, encoding)
+            .and_then(|_| self.take_buf());
+        self.buf.clear();
+        res
+    }
+
+    pub(crate) fn chapters(&self) -> &[(Box<str>, usize)] {
+        self.file.chapters()
+    }
+
+    pub(crate) fn chapter(&self) -> Option<usize> {
+        self.file.chapter()
+    }
+
+    // jump to the first page of chapter with given index.
+    pub(crate) fn try_chapter(&mut self, chapter: usize) -> Result<Option<RawPage>, Error> {
+        self.try_read(Direction::Chapter(chapter))
+    }
+
+    // decrypt current file with given password and retry the read stopped by it. password is
+    // remembered for the file until app exits when asked to and the read succeeds.
+    pub(crate) fn try_password(
+        &mut self,
+        password: &[u8],
+        remember: bool,
+    ) -> Result<Option<RawPage>, Error> {
+        self.file.set_password(password);
+        let direction = self.pending.unwrap_or(Direction::First);
+        let res = self.try_read(direction);
+        if remember && res.is_ok() && !self.directory_hint.as_os_str().is_empty() {
+            self.passwords
+                .insert(self.directory_hint.clone(), Box::from(password));
+        }
+        res
+    }
+
+    pub(crate) fn notice(&self) -> Option<String> {
+        self.file.notice()
+    }
+
+    // current page is read from right to left.
+    pub(crate) fn is_rtl(&self) -> bool {
+        self.file.is_rtl()
+    }
+
+    // current file is at its last page and there is no next file to move on to.
+    pub(crate) fn is_eof(&self) -> bool {
+        self.file.is_eof()
+    }
+
+    // current file is at its first page and there is no previous file to move back to.
+    pub(crate) fn is_head(&self) -> bool {
+        self.file.is_head()
+    }
+
+    pub(crate) fn try_next(&mut self) -> Result<Option<RawPage>, Error> {
         match self.try_read(Direction::Next)? {
             #[cfg(not(target_arch = "wasm32"))]
-            None if self.file.is_eof() && self.directory_hint.exists() => self.try_next_obj(),
+            None if self.file.is_eof() && self.has_directory_hint() => self.try_next_obj(),
             res => Ok(res),
         }
     }
 
-    pub(crate) fn try_skip(&mut self) -> Result<Option<ColorImage>, Error> {
+    pub(crate) fn try_skip(&mut self) -> Result<Option<RawPage>, Error> {
         self.try_read(Direction::Last)
     }
 
-    pub(crate) fn try_previous(&mut self) -> Result<Option<ColorImage>, Error> {
+    pub(crate) fn try_previous(&mut self) -> Result<Option<RawPage>, Error> {
         match self.try_read(Direction::Prev)? {
             #[cfg(not(target_arch = "wasm32"))]
-            None if self.file.is_head() && self.directory_hint.exists() => self.try_previous_obj(),
+            None if self.file.is_head() && self.has_directory_hint() => self.try_previous_obj(),
             res => Ok(res),
         }
     }
 
-    pub(crate) fn try_rewind(&mut self) -> Result<Option<ColorImage>, Error> {
+    pub(crate) fn try_rewind(&mut self) -> Result<Option<RawPage>, Error> {
         self.try_read(Direction::Offset(0))
     }
 
-    fn try_read(&mut self, direction: Direction) -> Result<Option<ColorImage>, Error> {
+    // read the page distance away from current one without moving to it. it's None when there
+    // is no such page in current book or it can not be read. next file on disk is never opened.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn peek(&mut self, distance: isize) -> Option<RawPage> {
+        let mut buf = Vec::new();
+        match self.file.peek(&mut buf, distance) {
+            Ok(true) if !buf.is_empty() => Some(RawPage::Image(buf)),
+            _ => None,
+        }
+    }
+
+    // cache key of the page distance away from current one. pages are known by their position
+    // so a cached page is found without reading it.
+    pub(crate) fn page_key(&self, distance: isize) -> Option<u64> {
+        let mut position = Vec::new();
+        self.file
+            .locate(distance, &mut position)
+            .then(|| cache::key(self.book, &position))
+    }
+
+    fn try_read(&mut self, direction: Direction) -> Result<Option<RawPage>, Error> {
         let res = self._try_read(direction);
         self.buf.clear();
+        self.pending = match res {
+            Err(Error::PasswordRequired | Error::WrongPassword) => Some(direction),
+            _ => None,
+        };
         res
     }
 
-    fn _try_read(&mut self, direction: Direction) -> Result<Option<ColorImage>, Error> {
+    // read the page a newly opened file starts from. a file without any page is an error so it
+    // does not leave a blank screen behind.
+    fn try_read_opened(&mut self, direction: Direction) -> Result<Option<RawPage>, Error> {
+        match self.try_read(direction)? {
+            None => Err(Error::Unsupported("no image page found in file".into())),
+            res => Ok(res),
+        }
+    }
+
+    fn _try_read(&mut self, direction: Direction) -> Result<Option<RawPage>, Error> {
         self.file.read(&mut self.buf, direction)?;
+        self.take_buf()
+    }
 
+    fn take_buf(&mut self) -> Result<Option<RawPage>, Error> {
         if self.buf.is_empty() {
-            Ok(None)
-        } else {
-            Ok(Some(crate::image::render_image(&self.buf, &self.res)))
+            return Ok(None);
+        }
+
+        match self.file.read_frames()? {
+            Some(frames) => Ok(Some(RawPage::Frames(frames))),
+            None => Ok(Some(RawPage::Image(std::mem::take(&mut self.buf)))),
+        }
+    }
+
+    // decode page to fit the base resolution.
+    pub(crate) fn render(&self, page: RawPage) -> Result<Page, Error> {
+        match page {
+            // tiff page is decoded by tif module. image crate misses much of tiff. (cmyk, ccitt)
+            RawPage::Image(buf) if Format::sniff(&buf) == Some(Format::Tiff) => {
+                let mut image = Vec::new();
+                tif::decode_page(&buf, &mut image)?;
+                crate::image::render_image(&image, &self.res)
+            }
+            RawPage::Image(buf) => crate::image::render_image(&buf, &self.res),
+            RawPage::Frames(frames) => crate::image::render_frames(&frames, &self.res),
         }
     }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 impl FileObj {
-    pub(crate) fn try_first(&mut self, path: PathBuf) -> Result<Option<ColorImage>, Error> {
-        self.try_open(path)?;
-        self.try_read(Direction::First)
+    pub(crate) fn try_first(&mut self, path: PathBuf) -> Result<Option<RawPage>, Error> {
+        let direction = self.try_open(path)?;
+        self.try_read_opened(direction)
     }
 
-    pub(crate) fn try_last(&mut self, path: PathBuf) -> Result<Option<ColorImage>, Error> {
+    pub(crate) fn try_last(&mut self, path: PathBuf) -> Result<Option<RawPage>, Error> {
         self.try_open(path)?;
-        self.try_read(Direction::Last)
+        self.try_read_opened(Direction::Last)
     }
 
-    fn try_open(&mut self, path: PathBuf) -> Result<(), Error> {
+    // open path and apply the password remembered for it.
+    fn try_open(&mut self, path: PathBuf) -> Result<Direction, Error> {
+        let direction = self.try_open_file(path)?;
+        if let Some(password) = self.passwords.get(&self.directory_hint) {
+            self.file.set_password(password);
+        }
+        Ok(direction)
+    }
+
+    // open path and return the direction where reading should start from.
+    fn try_open_file(&mut self, mut path: PathBuf) -> Result<Direction, Error> {
         self.buf.clear();
+        self.book += 1;
+        // split archive is known by it's leading part whichever part of the set is opened.
+        if let Some(leading) = split::leading_part(&path).filter(|p| p.exists()) {
+            path = leading;
+        }
         // regardless the outcome advance path to skip bad files.
         self.directory_hint = path;
         let path = &self.directory_hint;
 
-        self.file = if path.is_dir() {
-            Box::new(ListFile::try_from(path)?) as _
-        } else {
-            Box::new(ZipFile::try_from(path)?) as _
-        };
+        if path.is_dir() {
+            self.file = Box::new(ListFile::try_new(path, self.sort)?) as _;
+            return Ok(Direction::First);
+        }
 
-        Ok(())
+        let format = Format::detect(path)?;
+
+        // a loose image opens it's folder as the book and reading starts from the image.
+        if format == Some(Format::Image)
+            && let Some(dir) = path.parent().map(PathBuf::from)
+        {
+            let path = std::mem::replace(&mut self.directory_hint, dir);
+            let file = ListFile::try_new_flat(&self.directory_hint, self.sort)?;
+            let idx = file.position(&path).unwrap_or(0);
+            self.file = Box::new(file);
+            return Ok(Direction::Offset(idx));
+        }
+
+        self.file = open_archive(path, format, self.sort)?
+            .ok_or_else(|| Error::Unsupported("file is not an image or archive".into()))?;
+
+        Ok(Direction::First)
     }
 
-    fn try_next_obj(&mut self) -> Result<Option<ColorImage>, Error> {
-        match next_file_path(&self.directory_hint) {
+    fn has_directory_hint(&self) -> bool {
+        !self.directory_hint.as_os_str().is_empty()
+    }
+
+    fn try_next_obj(&mut self) -> Result<Option<RawPage>, Error> {
+        match self.siblings.next(&self.directory_hint, self.sort) {
             Ok(Some(path)) => self.try_first(path),
-            Ok(None) => {
-                self.directory_hint = PathBuf::default();
-                Ok(None)
-            }
+            Ok(None) => Ok(None),
             Err(e) => {
                 self.directory_hint = PathBuf::default();
                 Err(e)
@@ -402,13 +1295,10 @@ impl FileObj {
         }
     }
 
-    fn try_previous_obj(&mut self) -> Result<Option<ColorImage>, Error> {
-        match previous_file_path(&self.directory_hint) {
+    fn try_previous_obj(&mut self) -> Result<Option<RawPage>, Error> {
+        match self.siblings.previous(&self.directory_hint, self.sort) {
             Ok(Some(path)) => self.try_last(path),
-            Ok(None) => {
-                self.directory_hint = PathBuf::default();
-                Ok(None)
-            }
+            Ok(None) => Ok(None),
             Err(e) => {
                 self.directory_hint = PathBuf::default();
                 Err(e)
@@ -422,75 +1312,351 @@ impl FileObj {
     pub(crate) fn try_first(
         &mut self,
         buf: impl AsRef<[u8]> + 'static,
-    ) -> Result<Option<ColorImage>, Error> {
-        let file = ZipArchive::new(std::io::Cursor::new(buf))?;
-        let mut ordered_names = file
-            .file_names()
-            .map(Box::from)
-            .collect::<Box<[Box<str>]>>();
-        ordered_names.sort();
-
-        self.file = Box::new(ZipFile {
-            idx: 0,
-            ordered_names,
-            file,
-        }) as _;
+    ) -> Result<Option<RawPage>, Error> {
+        self.file = open_buf(buf, self.sort)?;
         self.buf.clear();
-        self.try_read(Direction::First)
+        self.book += 1;
+        self.try_read_opened(Direction::First)
+    }
+}
+
+// open file held in memory. browser does not give file name and archive nested in another
+// archive has no path on disk so they are told apart by their signatures.
+fn open_buf<B>(buf: B, sort: SortMode) -> Result<Box<dyn File>, Error>
+where
+    B: AsRef<[u8]> + 'static,
+{
+    let reader = std::io::Cursor::new(buf);
+    let bytes = reader.get_ref().as_ref();
+    match Format::sniff(bytes) {
+        Some(Format::SevenZ) => Ok(Box::new(sevenz::SevenZFile::try_new(reader, sort)?) as _),
+        Some(Format::Pdf) => Ok(Box::new(pdf::PdfFile::try_new(reader)?) as _),
+        Some(Format::Tar) => tarball::try_open(reader, sort),
+        Some(Format::Tiff) => Ok(Box::new(tif::TiffFile::try_new(reader)?) as _),
+        Some(Format::Image) => Ok(Box::new(ImageFile(reader)) as _),
+        #[cfg(not(target_arch = "wasm32"))]
+        Some(Format::Rar) => Ok(Box::new(rar::RarFile::try_from_buf(bytes, sort)?) as _),
+        #[cfg(target_arch = "wasm32")]
+        Some(Format::Rar) => Err(Error::Unsupported(
+            "rar archive can only be opened from a file on disk".into(),
+        )),
+        Some(Format::Zip) | None => open_zip(reader, sort),
     }
 }
 
+// a lone image without folder to browse. it's a single page book.
+struct ImageFile<B>(std::io::Cursor<B>);
+
+impl<B> File for ImageFile<B>
+where
+    B: AsRef<[u8]>,
+{
+    fn is_head(&self) -> bool {
+        true
+    }
+
+    fn is_eof(&self) -> bool {
+        true
+    }
+
+    fn read(&mut self, buf: &mut Vec<u8>, direction: Direction) -> Result<(), Error> {
+        match direction {
+            Direction::Next | Direction::Prev | Direction::Chapter(_) => {}
+            _ => buf.extend_from_slice(self.0.get_ref().as_ref()),
+        }
+        Ok(())
+    }
+
+    fn locate(&self, distance: isize, position: &mut Vec<usize>) -> bool {
+        if distance == 0 {
+            position.push(0);
+        }
+        distance == 0
+    }
+
+    fn sort(&mut self, _: SortMode) {}
+}
+
+const ZIP_EXTENSIONS: &[&str] = &["zip", "cbz", "epub", "ugoira"];
+
+const RAR_EXTENSIONS: &[&str] = &["rar", "cbr"];
+
+const SEVENZ_EXTENSIONS: &[&str] = &["7z", "cb7"];
+
+const TAR_EXTENSIONS: &[&str] = &["tar", "cbt", "tgz", "tzst"];
+
+// compressed tarball is told by it's double extension. a lone ".gz" or ".zst" is not a tarball.
+const COMPRESSED_TAR_SUFFIXES: &[&str] = &[".tar.gz", ".tar.zst"];
+
+fn is_tar_name(name: &str) -> bool {
+    let is_tar = name_extension(name)
+        .is_some_and(|ext| TAR_EXTENSIONS.iter().any(|e| e.eq_ignore_ascii_case(ext)));
+    let name = name.as_bytes();
+    is_tar
+        || COMPRESSED_TAR_SUFFIXES.iter().any(|suffix| {
+            name.len() >= suffix.len()
+                && name[name.len() - suffix.len()..].eq_ignore_ascii_case(suffix.as_bytes())
+        })
+}
+
+const PDF_EXTENSIONS: &[&str] = &["pdf"];
+
+const TIFF_EXTENSIONS: &[&str] = &["tif", "tiff"];
+
 #[cfg(not(target_arch = "wasm32"))]
-#[inline(never)]
-fn next_file_path(path: &PathBuf) -> Result<Option<PathBuf>, Error> {
-    match path.parent() {
-        Some(p) => {
-            assert!(p.is_dir());
+fn has_extension(path: &std::path::Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+}
 
-            let mut entries = std::fs::read_dir(p)?;
+// files shipped alongside pages that are never pages themselves.
+#[cfg(not(target_arch = "wasm32"))]
+const JUNK_EXTENSIONS: &[&str] = &["txt", "nfo", "url"];
 
-            for entry in entries.by_ref() {
-                let entry = entry?;
-                if &entry.path() == path {
-                    break;
-                }
-            }
+#[cfg(not(target_arch = "wasm32"))]
+const JUNK_NAMES: &[&str] = &["Thumbs.db", "desktop.ini"];
 
-            match entries.next() {
-                Some(entry) => {
-                    let entry = entry?;
-                    Ok(Some(entry.path()))
-                }
-                None => Ok(None),
-            }
+#[cfg(not(target_arch = "wasm32"))]
+fn is_junk(path: &std::path::Path) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    name.starts_with('.')
+        || JUNK_NAMES.iter().any(|n| n.eq_ignore_ascii_case(name))
+        || has_extension(path, JUNK_EXTENSIONS)
+        || split::is_trailing_part(path)
+}
+
+// open archive file with the implementation matching it's format. None when the file is not an
+// archive. (ComicInfo.xml, metadata and such left next to pages)
+#[cfg(not(target_arch = "wasm32"))]
+fn open_archive(
+    path: &std::path::Path,
+    format: Option<Format>,
+    sort: SortMode,
+) -> Result<Option<Box<dyn File>>, Error> {
+    if let Some((format, reader)) = split::try_open(path)? {
+        return match format {
+            Format::SevenZ => Ok(Box::new(sevenz::SevenZFile::try_new(reader, sort)?) as _),
+            Format::Tar => tarball::try_open(reader, sort),
+            _ => open_zip(reader, sort),
         }
-        None => Ok(None),
+        .map(Some);
     }
+
+    let file = match format {
+        Some(Format::Zip) => open_zip(std::fs::File::open(path)?, sort)?,
+        Some(Format::Rar) => Box::new(rar::RarFile::try_new(path, sort)?) as _,
+        Some(Format::SevenZ) => {
+            let file = std::fs::File::open(path)?;
+            Box::new(sevenz::SevenZFile::try_new(file, sort)?) as _
+        }
+        Some(Format::Tar) => tarball::try_open(std::fs::File::open(path)?, sort)?,
+        Some(Format::Pdf) => {
+            let file = std::fs::File::open(path)?;
+            Box::new(pdf::PdfFile::try_new(file)?) as _
+        }
+        Some(Format::Tiff) => {
+            let file = std::io::BufReader::new(std::fs::File::open(path)?);
+            Box::new(tif::TiffFile::try_new(file)?) as _
+        }
+        Some(Format::Image) | None => return Ok(None),
+    };
+    Ok(Some(file))
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-#[inline(never)]
-fn previous_file_path(path: &PathBuf) -> Result<Option<PathBuf>, Error> {
-    match path.parent() {
-        Some(p) => {
-            assert!(p.is_dir());
+use sibling::Siblings;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod sibling {
+    use super::*;
 
-            let entries = std::fs::read_dir(p)?;
+    use std::{fs, path::Path, time::SystemTime};
+
+    // naturally sorted listing of openable files and folders next to the current opened path.
+    // the listing is cached and only re-read when the parent folder or sort mode changes, or
+    // the parent folder is modified.
+    #[derive(Default)]
+    pub(super) struct Siblings {
+        dir: PathBuf,
+        modified: Option<SystemTime>,
+        sort: SortMode,
+        paths: Box<[PathBuf]>,
+    }
 
-            let mut res = None;
+    impl Siblings {
+        pub(super) fn next(
+            &mut self,
+            path: &Path,
+            sort: SortMode,
+        ) -> Result<Option<PathBuf>, Error> {
+            self.find(path, sort, |paths, idx| match idx {
+                Ok(idx) => paths.get(idx + 1),
+                // current path is gone. the one took its place is the next.
+                Err(idx) => paths.get(idx),
+            })
+        }
 
-            for entry in entries {
-                let entry = entry?;
-                let p = entry.path();
-                if &p == path {
-                    break;
-                }
+        pub(super) fn previous(
+            &mut self,
+            path: &Path,
+            sort: SortMode,
+        ) -> Result<Option<PathBuf>, Error> {
+            self.find(path, sort, |paths, idx| {
+                let idx = match idx {
+                    Ok(idx) | Err(idx) => idx,
+                };
+                idx.checked_sub(1).and_then(|idx| paths.get(idx))
+            })
+        }
+
+        fn find<F>(&mut self, path: &Path, sort: SortMode, f: F) -> Result<Option<PathBuf>, Error>
+        where
+            F: Fn(&[PathBuf], Result<usize, usize>) -> Option<&PathBuf>,
+        {
+            let Some(dir) = path.parent() else {
+                return Ok(None);
+            };
+
+            self.refresh(dir, sort, false)?;
+
+            let found = self.search(path, &f);
+
+            // sibling removed after the listing was cached. look again with a fresh listing.
+            if found.as_ref().is_some_and(|p| !p.exists()) {
+                self.refresh(dir, sort, true)?;
+                return Ok(self.search(path, &f));
+            }
+
+            Ok(found)
+        }
+
+        fn search<F>(&self, path: &Path, f: &F) -> Option<PathBuf>
+        where
+            F: Fn(&[PathBuf], Result<usize, usize>) -> Option<&PathBuf>,
+        {
+            let idx = self.paths.binary_search_by(|p| self.sort.cmp_path(p, path));
+            f(&self.paths, idx).cloned()
+        }
+
+        fn refresh(&mut self, dir: &Path, sort: SortMode, force: bool) -> Result<(), Error> {
+            let modified = fs::metadata(dir)?.modified().ok();
+
+            if !force
+                && self.dir == dir
+                && self.sort == sort
+                && self.modified.is_some()
+                && self.modified == modified
+            {
+                return Ok(());
+            }
 
-                res = Some(p);
+            let mut paths = Vec::new();
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                if is_openable(&path) {
+                    paths.push(path);
+                }
             }
+            paths.sort_by(|a, b| sort.cmp_path(a, b));
+
+            self.dir = dir.to_path_buf();
+            self.modified = modified;
+            self.sort = sort;
+            self.paths = paths.into_boxed_slice();
+
+            Ok(())
+        }
+    }
+
+    fn is_openable(path: &Path) -> bool {
+        let hidden = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with('.'));
 
-            Ok(res)
+        if hidden {
+            return false;
         }
-        None => Ok(None),
+
+        if path.is_dir() {
+            return true;
+        }
+
+        // split archive is listed once by it's leading part.
+        if let Some(leading) = split::leading_part(path) {
+            return leading == path;
+        }
+
+        // tiff is a book only when it has more than one page.
+        if has_extension(path, TIFF_EXTENSIONS) {
+            return fs::File::open(path)
+                .is_ok_and(|file| tif::is_multi_page(std::io::BufReader::new(file)));
+        }
+
+        // file extensions FileObj can open as a book. folders are always openable.
+        [
+            ZIP_EXTENSIONS,
+            RAR_EXTENSIONS,
+            SEVENZ_EXTENSIONS,
+            PDF_EXTENSIONS,
+        ]
+        .iter()
+        .any(|extensions| has_extension(path, extensions))
+            || path.to_str().is_some_and(is_tar_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grouped(names: &[&str]) -> (Vec<String>, Vec<(String, usize)>) {
+        let mut pages = names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        let chapters = group_chapters(&mut pages, |n| n);
+        let chapters = chapters.iter().map(|(n, i)| (n.to_string(), *i));
+        (pages, chapters.collect())
+    }
+
+    #[test]
+    fn folders_are_chapters() {
+        let (pages, chapters) = grouped(&["a/1.png", "a/2.png", "b/1.png", "b/2.png"]);
+        assert_eq!(pages, ["a/1.png", "a/2.png", "b/1.png", "b/2.png"]);
+        assert_eq!(chapters, [("a".into(), 0), ("b".into(), 2)]);
+        assert_eq!(
+            chapter_of(&group_chapters(&mut pages.clone(), |n| n), 3),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn files_of_folder_come_before_sub_folders() {
+        let (pages, chapters) = grouped(&["a/1.png", "a/b/1.png", "a/c.png", "d.png"]);
+        assert_eq!(pages, ["d.png", "a/1.png", "a/c.png", "a/b/1.png"]);
+        assert_eq!(
+            chapters,
+            [("/".into(), 0), ("a".into(), 1), ("a/b".into(), 3)]
+        );
+    }
+
+    #[test]
+    fn nested_archive_is_a_chapter() {
+        let (pages, chapters) = grouped(&["1.png", "2.zip", "3.cbz"]);
+        assert_eq!(pages, ["1.png", "2.zip", "3.cbz"]);
+        assert_eq!(
+            chapters,
+            [("/".into(), 0), ("2.zip".into(), 1), ("3.cbz".into(), 2)]
+        );
+    }
+
+    #[test]
+    fn single_chapter_is_no_chapter() {
+        let (pages, chapters) = grouped(&["a/1.png", "a/2.png"]);
+        assert_eq!(pages, ["a/1.png", "a/2.png"]);
+        assert!(chapters.is_empty());
+        assert_eq!(chapter_of(&[], 0), None);
     }
 }
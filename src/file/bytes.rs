@@ -0,0 +1,25 @@
+use std::io;
+
+use crate::error::Error;
+
+// little endian integers of archive records. callers make sure pos is inside buf.
+
+pub(super) fn u16_at(buf: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([buf[pos], buf[pos + 1]])
+}
+
+pub(super) fn u32_at(buf: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(buf[pos..pos + 4].try_into().unwrap())
+}
+
+pub(super) fn u64_at(buf: &[u8], pos: usize) -> u64 {
+    u64::from_le_bytes(buf[pos..pos + 8].try_into().unwrap())
+}
+
+// error of a file that does not follow it's format. kind names the format. (epub, split zip)
+pub(super) fn malformed(kind: &str, msg: &str) -> Error {
+    Error::Io(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("malformed {kind}: {msg}"),
+    ))
+}
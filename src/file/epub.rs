@@ -0,0 +1,270 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read, Seek},
+};
+
+use zip::ZipArchive;
+
+use crate::{error::Error, sort::SortMode};
+
+use super::{Direction, File, bytes::malformed, peek_index, read_zip_entry};
+
+pub(super) const CONTAINER: &str = "META-INF/container.xml";
+
+// fixed layout epub. pages are the images referenced by spine items in reading order.
+pub(super) struct EpubFile<R> {
+    idx: usize,
+    pages: Box<[Box<str>]>,
+    rtl: bool,
+    file: ZipArchive<R>,
+}
+
+impl<R> EpubFile<R>
+where
+    R: Read + Seek,
+{
+    pub(super) fn try_new(mut file: ZipArchive<R>) -> Result<Self, Error> {
+        let container = read_string(&mut file, CONTAINER)?;
+        let container = parse_xml(&container)?;
+        let opf_path = container
+            .descendants()
+            .find(|n| n.has_tag_name("rootfile"))
+            .and_then(|n| n.attribute("full-path"))
+            .ok_or_else(|| malformed("epub", "container.xml has no rootfile"))?
+            .to_owned();
+
+        let opf = read_string(&mut file, &opf_path)?;
+        let opf = parse_xml(&opf)?;
+
+        // manifest id -> (path inside zip, media type)
+        let manifest = opf
+            .descendants()
+            .filter(|n| n.has_tag_name("item"))
+            .filter_map(|n| {
+                let href = resolve(&opf_path, n.attribute("href")?);
+                Some((n.attribute("id")?, (href, n.attribute("media-type")?)))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let spine = opf
+            .descendants()
+            .find(|n| n.has_tag_name("spine"))
+            .ok_or_else(|| malformed("epub", "package document has no spine"))?;
+
+        let rtl = spine.attribute("page-progression-direction") == Some("rtl");
+
+        let mut pages = Vec::new();
+        for idref in spine
+            .children()
+            .filter(|n| n.has_tag_name("itemref"))
+            .filter_map(|n| n.attribute("idref"))
+        {
+            let Some((path, media_type)) = manifest.get(idref) else {
+                continue;
+            };
+
+            if media_type.starts_with("image/") {
+                pages.push(Box::from(path.as_str()));
+            } else if media_type.contains("html") || media_type.contains("svg") {
+                let doc = read_string(&mut file, path)?;
+                // a page without image is text content. it's skipped.
+                if let Some(src) = find_image(&doc) {
+                    pages.push(resolve(path, src).into_boxed_str());
+                }
+            }
+        }
+
+        if pages.is_empty() {
+            return Err(Error::Unsupported(
+                "epub has no image page. reflowable text can not be rendered".into(),
+            ));
+        }
+
+        Ok(Self {
+            idx: 0,
+            pages: pages.into_boxed_slice(),
+            rtl,
+            file,
+        })
+    }
+
+    fn read_by_index(&mut self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        let idx = index_of(&self.file, &self.pages[self.idx])?;
+        read_zip_entry(&mut self.file, idx, None, buf)
+    }
+}
+
+impl<R> File for EpubFile<R>
+where
+    R: Read + Seek,
+{
+    fn is_head(&self) -> bool {
+        self.idx == 0
+    }
+
+    fn is_eof(&self) -> bool {
+        self.idx == self.pages.len().saturating_sub(1)
+    }
+
+    fn read(&mut self, buf: &mut Vec<u8>, direction: Direction) -> Result<(), Error> {
+        match direction {
+            Direction::Next if self.is_eof() => return Ok(()),
+            Direction::Prev if self.is_head() => return Ok(()),
+            Direction::Next => self.idx += 1,
+            Direction::Prev => self.idx -= 1,
+            Direction::First => self.idx = 0,
+            Direction::Last => self.idx = self.pages.len().saturating_sub(1),
+            Direction::Offset(idx) => self.idx = idx.min(self.pages.len().saturating_sub(1)),
+            Direction::Chapter(_) => return Ok(()),
+        }
+
+        self.read_by_index(buf)
+    }
+
+    fn locate(&self, distance: isize, position: &mut Vec<usize>) -> bool {
+        peek_index(self.idx, self.pages.len(), distance)
+            .map(|at| position.push(at))
+            .is_some()
+    }
+
+    fn peek(&mut self, buf: &mut Vec<u8>, distance: isize) -> Result<bool, Error> {
+        let Some(at) = peek_index(self.idx, self.pages.len(), distance) else {
+            return Ok(false);
+        };
+        let idx = std::mem::replace(&mut self.idx, at);
+        let res = self.read_by_index(buf);
+        self.idx = idx;
+        res.map(|_| true)
+    }
+
+    // pages are always in spine order.
+    fn sort(&mut self, _: SortMode) {
+        self.idx = 0;
+    }
+
+    fn is_rtl(&self) -> bool {
+        self.rtl
+    }
+}
+
+fn read_string<R>(file: &mut ZipArchive<R>, name: &str) -> Result<String, Error>
+where
+    R: Read + Seek,
+{
+    let mut buf = Vec::new();
+    read_zip_entry(file, index_of(file, name)?, None, &mut buf)?;
+    String::from_utf8(buf).map_err(|e| Error::Io(io::Error::new(io::ErrorKind::InvalidData, e)))
+}
+
+fn index_of<R>(file: &ZipArchive<R>, name: &str) -> Result<usize, Error>
+where
+    R: Read + Seek,
+{
+    file.index_for_name(name)
+        .ok_or_else(|| Error::from(zip::result::ZipError::FileNotFound))
+}
+
+fn parse_xml(text: &str) -> Result<roxmltree::Document<'_>, Error> {
+    let opt = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    roxmltree::Document::parse_with_options(text, opt).map_err(Error::from)
+}
+
+// find the image source of a xhtml or svg page. content documents often carry html entities
+// and doctype a strict xml parser rejects so attributes are scanned leniently instead.
+fn find_image(doc: &str) -> Option<&str> {
+    let mut rest = doc;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let end = rest.find('>')?;
+        let tag = &rest[..end];
+        rest = &rest[end..];
+
+        let name_end = tag
+            .find(|c: char| c.is_ascii_whitespace() || c == '/')
+            .unwrap_or(tag.len());
+        let attrs = &tag[name_end..];
+        let src = match &tag[..name_end] {
+            "img" => attribute(attrs, "src"),
+            "image" | "svg:image" => {
+                attribute(attrs, "xlink:href").or_else(|| attribute(attrs, "href"))
+            }
+            _ => None,
+        };
+
+        if src.is_some() {
+            return src;
+        }
+    }
+    None
+}
+
+fn attribute<'a>(attrs: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = attrs;
+    loop {
+        let pos = rest.find(name)?;
+        let before = rest[..pos].chars().next_back();
+        rest = rest[pos + name.len()..].trim_start();
+        if !before.is_some_and(|c| c.is_ascii_whitespace()) {
+            continue;
+        }
+        let Some(value) = rest.strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        let quote = value.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            continue;
+        }
+        let value = &value[1..];
+        return value.find(quote).map(|end| &value[..end]);
+    }
+}
+
+// resolve href relative to the document at base path. both are zip entry names.
+fn resolve(base: &str, href: &str) -> String {
+    let href = href.split(['#', '?']).next().unwrap_or_default();
+    let href = percent_decode(href);
+
+    let mut components = match base.rsplit_once('/') {
+        Some((dir, _)) if !href.starts_with('/') => dir.split('/').collect(),
+        _ => Vec::new(),
+    };
+
+    for c in href.split('/') {
+        match c {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            c => components.push(c),
+        }
+    }
+
+    components.join("/")
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).unwrap_or_else(|_| s.to_owned())
+}
@@ -0,0 +1,171 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::{io::Read, path::Path};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::error::Error;
+
+use super::tarball;
+
+// bytes needed to tell every format apart. (tar header magic sits at offset 257)
+#[cfg(not(target_arch = "wasm32"))]
+const SNIFF_LEN: u64 = 512;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum Format {
+    Image,
+    Zip,
+    Rar,
+    SevenZ,
+    Tar,
+    Pdf,
+    Tiff,
+}
+
+impl Format {
+    // tell file format apart by it's leading bytes.
+    pub(super) fn sniff(head: &[u8]) -> Option<Self> {
+        const ZIP_SIGNATURES: &[&[u8]] = &[b"PK\x03\x04", b"PK\x05\x06", b"PK\x07\x08"];
+        const RAR_SIGNATURE: &[u8] = b"Rar!\x1A\x07";
+        const SEVENZ_SIGNATURE: &[u8] = b"7z\xBC\xAF\x27\x1C";
+        const PDF_SIGNATURE: &[u8] = b"%PDF-";
+        // little and big endian of classic and big tiff.
+        const TIFF_SIGNATURES: &[&[u8]] = &[b"II*\0", b"MM\0*", b"II+\0", b"MM\0+"];
+
+        if ZIP_SIGNATURES.iter().any(|sig| head.starts_with(sig)) {
+            Some(Self::Zip)
+        } else if head.starts_with(RAR_SIGNATURE) {
+            Some(Self::Rar)
+        } else if head.starts_with(SEVENZ_SIGNATURE) {
+            Some(Self::SevenZ)
+        } else if head.starts_with(PDF_SIGNATURE) {
+            Some(Self::Pdf)
+        } else if TIFF_SIGNATURES.iter().any(|sig| head.starts_with(sig)) {
+            Some(Self::Tiff)
+        } else if tarball::is_tar(head) {
+            Some(Self::Tar)
+        } else if image::guess_format(head).is_ok() || crate::image::is_jxl(head) {
+            Some(Self::Image)
+        } else {
+            None
+        }
+    }
+
+    // sniff file content and fall back to file extension when content is not recognized.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(super) fn detect(path: &Path) -> Result<Option<Self>, Error> {
+        let mut head = Vec::new();
+        std::fs::File::open(path)?
+            .take(SNIFF_LEN)
+            .read_to_end(&mut head)?;
+        match Self::sniff(&head) {
+            // single page tiff is an image.
+            Some(Self::Tiff) => {
+                let file = std::io::BufReader::new(std::fs::File::open(path)?);
+                Ok(Some(if super::tif::is_multi_page(file) {
+                    Self::Tiff
+                } else {
+                    Self::Image
+                }))
+            }
+            format => Ok(format.or_else(|| Self::from_extension(path))),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn from_extension(path: &Path) -> Option<Self> {
+        use super::{
+            IMAGE_EXTENSIONS, PDF_EXTENSIONS, RAR_EXTENSIONS, SEVENZ_EXTENSIONS, TIFF_EXTENSIONS,
+            ZIP_EXTENSIONS, has_extension, is_tar_name,
+        };
+
+        if path.to_str().is_some_and(is_tar_name) {
+            return Some(Self::Tar);
+        }
+
+        [
+            (IMAGE_EXTENSIONS, Self::Image),
+            (ZIP_EXTENSIONS, Self::Zip),
+            (RAR_EXTENSIONS, Self::Rar),
+            (SEVENZ_EXTENSIONS, Self::SevenZ),
+            (PDF_EXTENSIONS, Self::Pdf),
+            (TIFF_EXTENSIONS, Self::Tiff),
+        ]
+        .into_iter()
+        .find_map(|(extensions, format)| has_extension(path, extensions).then_some(format))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{Compression, write::GzEncoder};
+
+    use super::*;
+
+    fn tar() -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_ustar();
+        header.set_size(4);
+        builder
+            .append_data(&mut header, "001.png", &b"page"[..])
+            .unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn formats_are_told_by_leading_bytes() {
+        for (head, format) in [
+            (&b"PK\x03\x04rest"[..], Format::Zip),
+            (b"PK\x05\x06", Format::Zip),
+            (b"Rar!\x1A\x07\x01\x00", Format::Rar),
+            (b"7z\xBC\xAF\x27\x1C\x00\x04", Format::SevenZ),
+            (b"%PDF-1.7\n", Format::Pdf),
+            (b"II*\0\x08\0\0\0", Format::Tiff),
+            (b"MM\0+\0\x08\0\0", Format::Tiff),
+            (b"\x89PNG\r\n\x1A\n", Format::Image),
+            (b"\xFF\xD8\xFF\xE0", Format::Image),
+            (b"\xFF\x0A", Format::Image),
+        ] {
+            assert!(Format::sniff(head) == Some(format));
+        }
+    }
+
+    #[test]
+    fn plain_and_compressed_tar_are_told() {
+        let tar = tar();
+        assert!(Format::sniff(&tar) == Some(Format::Tar));
+
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(&tar).unwrap();
+        let gz = gz.finish().unwrap();
+        // only the leading bytes are sniffed.
+        assert!(Format::sniff(&gz[..gz.len().min(512)]) == Some(Format::Tar));
+
+        // gzip of something else is not a tar.
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(&[1; 1024]).unwrap();
+        assert!(Format::sniff(&gz.finish().unwrap()).is_none());
+    }
+
+    #[test]
+    fn unknown_bytes_are_no_format() {
+        assert!(Format::sniff(b"").is_none());
+        assert!(Format::sniff(b"plain text").is_none());
+        assert!(Format::sniff(&tar()[..100]).is_none());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn unknown_content_falls_back_to_extension() {
+        let dir = std::env::temp_dir().join(format!("shin-format-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("book.cbz");
+        std::fs::write(&path, b"damaged").unwrap();
+        assert!(Format::detect(&path).unwrap() == Some(Format::Zip));
+        // content wins over extension.
+        std::fs::write(&path, b"%PDF-1.4\n").unwrap();
+        assert!(Format::detect(&path).unwrap() == Some(Format::Pdf));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,475 @@
+use std::{borrow::Cow, io::Read};
+
+use image::{
+    ImageError,
+    error::{DecodingError, ImageFormatHint},
+};
+use lopdf::{DecompressError, Dictionary, Document, Object, ObjectId, Stream};
+
+use crate::{error::Error, limit, sort::SortMode};
+
+use super::{
+    Direction, File, peek_index,
+    raster::{Samples, cmyk_to_rgb},
+};
+
+// depth limit of form xobjects nested inside each other.
+const MAX_FORM_DEPTH: usize = 4;
+
+// image only pdf where every page is a scanned or digital image. each page yields the largest
+// image drawn on it. pages drawn with vector content are not rendered.
+pub(super) struct PdfFile {
+    idx: usize,
+    pages: Box<[ObjectId]>,
+    doc: Document,
+}
+
+impl PdfFile {
+    pub(super) fn try_new(reader: impl Read) -> Result<Self, Error> {
+        let doc = Document::load_from(reader)?;
+        let pages = doc.page_iter().collect();
+        Ok(Self { idx: 0, pages, doc })
+    }
+
+    fn read_by_index(&mut self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        let Some(&page) = self.pages.get(self.idx) else {
+            return Ok(());
+        };
+
+        let doc = &self.doc;
+        let (resources, resource_ids) = doc.get_page_resources(page)?;
+
+        let mut images = Vec::new();
+        resources
+            .into_iter()
+            .chain(
+                resource_ids
+                    .into_iter()
+                    .filter_map(|id| doc.get_dictionary(id).ok()),
+            )
+            .for_each(|resources| collect_images(doc, resources, 0, &mut images));
+
+        let image = images
+            .into_iter()
+            .max_by_key(|image| {
+                let dict = &image.dict;
+                int(dict, b"Width").unwrap_or(0) * int(dict, b"Height").unwrap_or(0)
+            })
+            .ok_or_else(|| {
+                Error::Unsupported(format!(
+                    "page {} of pdf has no embedded image. vector content can not be rendered",
+                    self.idx + 1
+                ))
+            })?;
+
+        decode_image(doc, image, buf)
+    }
+}
+
+impl File for PdfFile {
+    fn is_head(&self) -> bool {
+        self.idx == 0
+    }
+
+    fn is_eof(&self) -> bool {
+        self.idx == self.pages.len().saturating_sub(1)
+    }
+
+    fn read(&mut self, buf: &mut Vec<u8>, direction: Direction) -> Result<(), Error> {
+        match direction {
+            Direction::Next if self.is_eof() => return Ok(()),
+            Direction::Prev if self.is_head() => return Ok(()),
+            Direction::Next => self.idx += 1,
+            Direction::Prev => self.idx -= 1,
+            Direction::First => self.idx = 0,
+            Direction::Last => self.idx = self.pages.len().saturating_sub(1),
+            Direction::Offset(idx) => self.idx = idx.min(self.pages.len().saturating_sub(1)),
+            Direction::Chapter(_) => return Ok(()),
+        }
+
+        self.read_by_index(buf)
+    }
+
+    fn locate(&self, distance: isize, position: &mut Vec<usize>) -> bool {
+        peek_index(self.idx, self.pages.len(), distance)
+            .map(|at| position.push(at))
+            .is_some()
+    }
+
+    fn peek(&mut self, buf: &mut Vec<u8>, distance: isize) -> Result<bool, Error> {
+        let Some(at) = peek_index(self.idx, self.pages.len(), distance) else {
+            return Ok(false);
+        };
+        let idx = std::mem::replace(&mut self.idx, at);
+        let res = self.read_by_index(buf);
+        self.idx = idx;
+        res.map(|_| true)
+    }
+
+    // pages are always in document order.
+    fn sort(&mut self, _: SortMode) {
+        self.idx = 0;
+    }
+}
+
+fn collect_images<'a>(
+    doc: &'a Document,
+    resources: &'a Dictionary,
+    depth: usize,
+    images: &mut Vec<&'a Stream>,
+) {
+    let Ok(xobjects) = resources
+        .get(b"XObject")
+        .and_then(|obj| doc.dereference(obj))
+        .and_then(|(_, obj)| obj.as_dict())
+    else {
+        return;
+    };
+
+    for (_, obj) in xobjects.iter() {
+        let Ok((_, Object::Stream(stream))) = doc.dereference(obj) else {
+            continue;
+        };
+
+        match stream.dict.get(b"Subtype").and_then(Object::as_name) {
+            Ok(b"Image") => {
+                let is_mask = stream
+                    .dict
+                    .get(b"ImageMask")
+                    .and_then(Object::as_bool)
+                    .unwrap_or(false);
+                if !is_mask {
+                    images.push(stream);
+                }
+            }
+            Ok(b"Form") if depth < MAX_FORM_DEPTH => {
+                if let Ok((_, Object::Dictionary(resources))) = stream
+                    .dict
+                    .get(b"Resources")
+                    .and_then(|obj| doc.dereference(obj))
+                {
+                    collect_images(doc, resources, depth + 1, images);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// write image xobject to buf in a format render_image can decode.
+fn decode_image(doc: &Document, image: &Stream, buf: &mut Vec<u8>) -> Result<(), Error> {
+    let dict = &image.dict;
+    let width = int(dict, b"Width")? as u32;
+    let height = int(dict, b"Height")? as u32;
+    // raw samples are decoded here before render_image sees them.
+    limit::limits().check_pixels(width, height)?;
+
+    let filters = image.filters().unwrap_or_default();
+
+    // image codec is always the last filter. filters before it are general purpose compressions.
+    let (codec, data) = match filters.split_last() {
+        Some((codec, rest))
+            if matches!(
+                *codec,
+                b"DCTDecode" | b"JPXDecode" | b"CCITTFaxDecode" | b"JBIG2Decode"
+            ) =>
+        {
+            let data = if rest.is_empty() {
+                Cow::Borrowed(image.content.as_slice())
+            } else {
+                let mut dict = dict.clone();
+                let rest = rest.iter().map(|f| Object::Name(f.to_vec())).collect();
+                dict.set("Filter", Object::Array(rest));
+                Cow::Owned(plain_content(&Stream::new(dict, image.content.clone()))?)
+            };
+            (Some(*codec), data)
+        }
+        _ => (None, Cow::Owned(plain_content(image)?)),
+    };
+
+    match codec {
+        Some(b"DCTDecode") => {
+            buf.extend_from_slice(&data);
+            Ok(())
+        }
+        Some(b"JPXDecode") => decode_jpx(&data, buf),
+        Some(b"CCITTFaxDecode") => {
+            let params = dict.get(b"DecodeParms").and_then(Object::as_dict).ok();
+            let samples = decode_ccitt(&data, params, width, height)?;
+            let samples = Samples {
+                data: &samples,
+                width,
+                height,
+                bits: 8,
+            };
+            write_samples(samples, &ColorSpace::Gray, decode_inverted(dict), buf)
+        }
+        Some(codec) => Err(Error::Unsupported(format!(
+            "pdf image filter {} is not supported",
+            String::from_utf8_lossy(codec)
+        ))),
+        None => {
+            let color_space = match dict.get(b"ColorSpace") {
+                Ok(obj) => color_space(doc, obj)?,
+                Err(_) => ColorSpace::Gray,
+            };
+            let samples = Samples {
+                data: &data,
+                width,
+                height,
+                bits: int(dict, b"BitsPerComponent").unwrap_or(8) as u8,
+            };
+            write_samples(samples, &color_space, decode_inverted(dict), buf)
+        }
+    }
+}
+
+// undo general purpose compressions of stream. decoding stops once it's output goes over the
+// entry size limit.
+fn plain_content(stream: &Stream) -> Result<Vec<u8>, Error> {
+    let limits = limit::limits();
+    let max = usize::try_from(limits.max_entry_size).unwrap_or(usize::MAX);
+    let content = stream
+        .get_plain_content_with_limit(max)
+        .map_err(|e| match e {
+            lopdf::Error::Decompress(DecompressError::MemoryLimitExceeded { .. }) => {
+                Error::LimitExceeded(format!(
+                    "pdf stream is over {} bytes uncompressed",
+                    limits.max_entry_size
+                ))
+            }
+            e => e.into(),
+        })?;
+    limits.check_entry(
+        "pdf stream",
+        content.len() as u64,
+        stream.content.len() as u64,
+    )?;
+    Ok(content)
+}
+
+fn int(dict: &Dictionary, key: &[u8]) -> Result<i64, Error> {
+    dict.get(key).and_then(Object::as_i64).map_err(Into::into)
+}
+
+// a /Decode [1 0] array flips the value of samples. it's common for bi-level scans.
+fn decode_inverted(dict: &Dictionary) -> bool {
+    dict.get(b"Decode")
+        .and_then(Object::as_array)
+        .ok()
+        .and_then(|arr| Some((arr.first()?.as_float().ok()?, arr.get(1)?.as_float().ok()?)))
+        .is_some_and(|(min, max)| min > max)
+}
+
+enum ColorSpace {
+    Gray,
+    Rgb,
+    Cmyk,
+    Indexed(Box<ColorSpace>, Vec<u8>),
+}
+
+impl ColorSpace {
+    fn components(&self) -> usize {
+        match self {
+            Self::Gray | Self::Indexed(..) => 1,
+            Self::Rgb => 3,
+            Self::Cmyk => 4,
+        }
+    }
+}
+
+fn color_space(doc: &Document, obj: &Object) -> Result<ColorSpace, Error> {
+    let (_, obj) = doc.dereference(obj)?;
+
+    let (name, args) = match obj {
+        Object::Name(name) => (name.as_slice(), &[][..]),
+        Object::Array(arr) => match arr.split_first() {
+            Some((name, args)) => (name.as_name()?, args),
+            None => return Ok(ColorSpace::Gray),
+        },
+        _ => return Ok(ColorSpace::Gray),
+    };
+
+    match name {
+        b"DeviceGray" | b"CalGray" | b"G" => Ok(ColorSpace::Gray),
+        b"DeviceRGB" | b"CalRGB" | b"RGB" => Ok(ColorSpace::Rgb),
+        b"DeviceCMYK" | b"CMYK" => Ok(ColorSpace::Cmyk),
+        b"ICCBased" => {
+            let n = args
+                .first()
+                .and_then(|obj| doc.dereference(obj).ok())
+                .and_then(|(_, obj)| obj.as_stream().ok())
+                .and_then(|stream| int(&stream.dict, b"N").ok());
+            match n {
+                Some(1) => Ok(ColorSpace::Gray),
+                Some(4) => Ok(ColorSpace::Cmyk),
+                _ => Ok(ColorSpace::Rgb),
+            }
+        }
+        b"Indexed" | b"I" => {
+            let [base, _, lookup] = args else {
+                return Err(Error::Unsupported(
+                    "malformed pdf indexed color space".into(),
+                ));
+            };
+            let base = color_space(doc, base)?;
+            let lookup = match doc.dereference(lookup)?.1 {
+                Object::String(bytes, _) => bytes.clone(),
+                Object::Stream(stream) => plain_content(stream)?,
+                _ => Vec::new(),
+            };
+            Ok(ColorSpace::Indexed(Box::new(base), lookup))
+        }
+        name => Err(Error::Unsupported(format!(
+            "pdf color space {} is not supported",
+            String::from_utf8_lossy(name)
+        ))),
+    }
+}
+
+fn write_samples(
+    samples: Samples<'_>,
+    color_space: &ColorSpace,
+    inverted: bool,
+    buf: &mut Vec<u8>,
+) -> Result<(), Error> {
+    if !matches!(samples.bits, 1 | 2 | 4 | 8 | 16) {
+        return Err(Error::Unsupported(format!(
+            "pdf image with {} bits per component is not supported",
+            samples.bits
+        )));
+    }
+
+    let invert = |v: u8| if inverted { 255 - v } else { v };
+
+    let (channels, pixels) = match color_space {
+        ColorSpace::Gray => (1, samples.iter(1, true).map(invert).collect()),
+        ColorSpace::Rgb => (3, samples.iter(3, true).map(invert).collect()),
+        ColorSpace::Cmyk => {
+            let cmyk = samples.iter(4, true).map(invert).collect::<Vec<_>>();
+            (3, cmyk_to_rgb(&cmyk))
+        }
+        ColorSpace::Indexed(base, lookup) => {
+            let n = base.components();
+            let mut pixels = Vec::new();
+            for idx in samples.iter(1, false) {
+                let start = idx as usize * n;
+                match lookup.get(start..start + n) {
+                    Some(color) => pixels.extend_from_slice(color),
+                    None => pixels.extend(std::iter::repeat_n(0, n)),
+                }
+            }
+            match **base {
+                ColorSpace::Cmyk => (3, cmyk_to_rgb(&pixels)),
+                ColorSpace::Gray => (1, pixels),
+                _ => (3, pixels),
+            }
+        }
+    };
+
+    crate::image::write_pnm(buf, samples.width, samples.height, channels, &pixels);
+
+    Ok(())
+}
+
+fn decode_jpx(data: &[u8], buf: &mut Vec<u8>) -> Result<(), Error> {
+    use hayro_jpeg2000::{ColorSpace as JpxColorSpace, DecodeSettings, Image};
+
+    let image = Image::new(data, &DecodeSettings::default()).map_err(jpx_error)?;
+    let pixels = image.decode().map_err(jpx_error)?;
+
+    let n = image.color_space().num_channels() as usize;
+    let stride = n + image.has_alpha() as usize;
+
+    // alpha channel is dropped. page is drawn on an opaque background.
+    let color = pixels.chunks_exact(stride).flat_map(|px| &px[..n]);
+
+    let (channels, pixels) = match (image.color_space(), n) {
+        (JpxColorSpace::CMYK, _) | (_, 4) => (3, cmyk_to_rgb(&color.copied().collect::<Vec<_>>())),
+        (_, 1) => (1, color.copied().collect()),
+        _ => (3, color.copied().collect()),
+    };
+
+    crate::image::write_pnm(buf, image.width(), image.height(), channels, &pixels);
+
+    Ok(())
+}
+
+fn jpx_error(e: hayro_jpeg2000::error::DecodeError) -> Error {
+    Error::Image(ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Name("JPEG 2000".into()),
+        e,
+    )))
+}
+
+// decode ccitt fax data into 8 bits gray samples. the value of a pixel is the same as 1 bit
+// sample would have in the pdf. (black is 0 unless BlackIs1 is set)
+fn decode_ccitt(
+    data: &[u8],
+    params: Option<&Dictionary>,
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>, Error> {
+    let param = |key: &[u8]| params.and_then(|p| p.get(key).ok());
+
+    // fax decoder counts pixels of a row in 16 bits.
+    let dimension = |key: &[u8], default: u32| {
+        let value = param(key)
+            .and_then(|v| v.as_i64().ok())
+            .unwrap_or(default as i64);
+        u16::try_from(value).map_err(|_| {
+            Error::Unsupported(format!(
+                "pdf ccitt fax image with {} {value} is not supported",
+                String::from_utf8_lossy(key)
+            ))
+        })
+    };
+
+    let k = param(b"K").and_then(|k| k.as_i64().ok()).unwrap_or(0);
+    let columns = dimension(b"Columns", width)?;
+    let rows = dimension(b"Rows", height)?;
+    let black_is_1 = param(b"BlackIs1")
+        .and_then(|b| b.as_bool().ok())
+        .unwrap_or(false);
+
+    let (black, white) = if black_is_1 { (255, 0) } else { (0, 255) };
+
+    // rows are Columns pixels long and copied into rows of image width. pixels a row lacks are
+    // white and rows past image height are dropped.
+    let (width, height) = (width as usize, height as usize);
+    let mut samples = Vec::with_capacity(width * height);
+    let mut lines = 0;
+    let mut on_line = |transitions: &[u16]| {
+        if lines == height {
+            return;
+        }
+        lines += 1;
+        let pels = fax::decoder::pels(transitions, columns).map(|c| match c {
+            fax::Color::Black => black,
+            fax::Color::White => white,
+        });
+        samples.extend(pels.chain(std::iter::repeat(white)).take(width));
+    };
+
+    let res = match k {
+        k if k < 0 => fax::decoder::decode_g4(data.iter().copied(), columns, Some(rows), on_line),
+        0 => fax::decoder::decode_g3(data.iter().copied(), &mut on_line),
+        _ => {
+            return Err(Error::Unsupported(
+                "pdf ccitt fax image with mixed 2d encoding is not supported".into(),
+            ));
+        }
+    };
+
+    if res.is_none() && samples.is_empty() {
+        return Err(Error::Image(ImageError::Decoding(DecodingError::new(
+            ImageFormatHint::Name("CCITT fax".into()),
+            "malformed ccitt fax data",
+        ))));
+    }
+
+    // pad missing rows with white.
+    samples.resize(width * height, white);
+
+    Ok(samples)
+}
@@ -0,0 +1,205 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use unrar::{Archive, CursorBeforeHeader, OpenArchive, Process};
+
+use crate::{cancel, error::Error, limit, sort::SortMode};
+
+use super::{Direction, File, chapter_of, group_chapters, is_page_name, peek_index};
+
+// rar archive is read through unrar library which can only walk entries forward from the start
+// of archive. an opened cursor is kept around so reading pages in archive order does not reopen
+// (and for solid archive decompress again) the entries already walked through.
+pub(super) struct RarFile {
+    idx: usize,
+    path: PathBuf,
+    // name of entry and it's position inside archive.
+    ordered_names: Box<[(Box<str>, usize)]>,
+    // folders inside archive as chapters. (name, index of it's first page)
+    chapters: Box<[(Box<str>, usize)]>,
+    // bytes of archive file and sum of sizes of all entries uncompressed. unrar tells no compressed
+    // size of entry so it's estimated from them.
+    packed: u64,
+    unpacked: u64,
+    // opened archive and the position of entry it would read next.
+    cursor: Option<(usize, OpenArchive<Process, CursorBeforeHeader>)>,
+    // file the archive is written to when it's opened from memory. it's dropped after cursor so
+    // the file is closed before it's removed.
+    temp: Option<TempFile>,
+}
+
+// file removed when dropped.
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+impl RarFile {
+    pub(super) fn try_new(path: &Path, sort: SortMode) -> Result<Self, Error> {
+        let mut ordered_names = Vec::new();
+        let mut unpacked = 0u64;
+        let mut split = false;
+
+        for (pos, header) in Archive::new(path).open_for_listing()?.enumerate() {
+            let header = header?;
+            if header.is_file() {
+                unpacked = unpacked.saturating_add(header.unpacked_size);
+                split |= header.is_split();
+                let name = header.filename.to_string_lossy().replace('\\', "/");
+                if is_page_name(&name) {
+                    ordered_names.push((name.into_boxed_str(), pos));
+                }
+            }
+        }
+
+        let mut this = Self {
+            idx: 0,
+            path: path.to_path_buf(),
+            ordered_names: ordered_names.into_boxed_slice(),
+            chapters: Box::default(),
+            // other volumes of the archive are not counted. it's compression is left unchecked.
+            packed: if split {
+                unpacked
+            } else {
+                fs::metadata(path)?.len()
+            },
+            unpacked,
+            cursor: None,
+            temp: None,
+        };
+        this.sort(sort);
+        Ok(this)
+    }
+
+    // open rar held in memory. (nested inside another archive) unrar only reads from disk so
+    // it's written to a temp file that lives as long as the archive.
+    pub(super) fn try_from_buf(buf: &[u8], sort: SortMode) -> Result<Self, Error> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let name = format!(
+            "shin-hentai-{}-{}.rar",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        );
+        let temp = TempFile(std::env::temp_dir().join(name));
+        fs::write(&temp.0, buf)?;
+
+        let mut this = Self::try_new(&temp.0, sort)?;
+        this.temp = Some(temp);
+        Ok(this)
+    }
+
+    fn read_by_index(&mut self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        let Some(&(_, pos)) = self.ordered_names.get(self.idx) else {
+            return Ok(());
+        };
+
+        let (mut at, mut archive) = match self.cursor.take() {
+            Some((at, archive)) if at <= pos => (at, archive),
+            _ => (0, Archive::new(&self.path).open_for_processing()?),
+        };
+
+        loop {
+            // skipping an entry of solid archive decompresses it. cancelled walk keeps the
+            // cursor where it stopped so the next read goes on from there.
+            if let Err(e) = cancel::check() {
+                self.cursor = Some((at, archive));
+                return Err(e.into());
+            }
+            let Some(header) = archive.read_header()? else {
+                return Ok(());
+            };
+            if at == pos {
+                let entry = header.entry();
+                let name = entry.filename.to_string_lossy();
+                let limit = limit::limits().check_packed_entry(
+                    &name,
+                    entry.unpacked_size,
+                    self.unpacked,
+                    self.packed,
+                );
+                if let Err(e) = limit {
+                    self.cursor = Some((at + 1, header.skip()?));
+                    return Err(e);
+                }
+                let (data, archive) = header.read()?;
+                self.cursor = Some((at + 1, archive));
+                buf.extend_from_slice(&data);
+                return Ok(());
+            }
+            archive = header.skip()?;
+            at += 1;
+        }
+    }
+}
+
+impl File for RarFile {
+    fn is_head(&self) -> bool {
+        self.idx == 0
+    }
+
+    fn is_eof(&self) -> bool {
+        self.idx == self.ordered_names.len().saturating_sub(1)
+    }
+
+    fn read(&mut self, buf: &mut Vec<u8>, direction: Direction) -> Result<(), Error> {
+        match direction {
+            Direction::Next if self.is_eof() => return Ok(()),
+            Direction::Prev if self.is_head() => return Ok(()),
+            Direction::Next => self.idx += 1,
+            Direction::Prev => self.idx -= 1,
+            Direction::First => self.idx = 0,
+            Direction::Last => self.idx = self.ordered_names.len().saturating_sub(1),
+            Direction::Offset(idx) => {
+                self.idx = idx.min(self.ordered_names.len().saturating_sub(1))
+            }
+            Direction::Chapter(n) => match self.chapters.get(n) {
+                Some((_, start)) => self.idx = *start,
+                None => return Ok(()),
+            },
+        }
+
+        self.read_by_index(buf)
+    }
+
+    fn locate(&self, distance: isize, position: &mut Vec<usize>) -> bool {
+        peek_index(self.idx, self.ordered_names.len(), distance)
+            .map(|at| position.push(at))
+            .is_some()
+    }
+
+    // archive is only walked forward. peeking back would walk it again from the start.
+    fn peek(&mut self, buf: &mut Vec<u8>, distance: isize) -> Result<bool, Error> {
+        if distance < 0 {
+            return Ok(false);
+        }
+        let Some(at) = peek_index(self.idx, self.ordered_names.len(), distance) else {
+            return Ok(false);
+        };
+        let idx = std::mem::replace(&mut self.idx, at);
+        let res = self.read_by_index(buf);
+        self.idx = idx;
+        res.map(|_| true)
+    }
+
+    fn sort(&mut self, sort: SortMode) {
+        self.ordered_names
+            .sort_by(|(a, _), (b, _)| sort.cmp_name(a, b));
+        self.chapters = group_chapters(&mut self.ordered_names, |(name, _)| name);
+        self.idx = 0;
+    }
+
+    fn chapters(&self) -> &[(Box<str>, usize)] {
+        &self.chapters
+    }
+
+    fn chapter(&self) -> Option<usize> {
+        chapter_of(&self.chapters, self.idx)
+    }
+}
@@ -0,0 +1,50 @@
+// uncompressed samples of a pdf or tiff image. samples of a row are packed with given bits and
+// every row starts at a byte boundary.
+pub(super) struct Samples<'a> {
+    pub(super) data: &'a [u8],
+    pub(super) width: u32,
+    pub(super) height: u32,
+    pub(super) bits: u8,
+}
+
+impl Samples<'_> {
+    // iterate samples of every component as 8 bits values. rows are padded to byte boundary.
+    // samples of indexed color space are palette indexes and must not be scaled.
+    pub(super) fn iter(&self, components: usize, scale: bool) -> impl Iterator<Item = u8> + '_ {
+        let per_row = self.width as usize * components;
+        let row_bytes = (per_row * self.bits as usize).div_ceil(8);
+        let bits = self.bits as usize;
+        let max = (1u32 << bits.min(8)) - 1;
+
+        self.data
+            .chunks(row_bytes.max(1))
+            .take(self.height as usize)
+            .flat_map(move |row| {
+                (0..per_row).map(move |i| match bits {
+                    8 => row.get(i).copied().unwrap_or(0),
+                    // keep the most significant byte of 16 bits samples.
+                    16 => row.get(i * 2).copied().unwrap_or(0),
+                    _ => {
+                        let bit = i * bits;
+                        let byte = row.get(bit / 8).copied().unwrap_or(0) as u32;
+                        let v = (byte >> (8 - bits - bit % 8)) & max;
+                        if scale {
+                            (v * 255 / max) as u8
+                        } else {
+                            v as u8
+                        }
+                    }
+                })
+            })
+    }
+}
+
+// convert 8 bits cmyk pixels to rgb without a color profile.
+pub(super) fn cmyk_to_rgb(cmyk: &[u8]) -> Vec<u8> {
+    cmyk.chunks_exact(4)
+        .flat_map(|px| {
+            let k = 255 - px[3] as u32;
+            [0, 1, 2].map(|i| ((255 - px[i] as u32) * k / 255) as u8)
+        })
+        .collect()
+}
@@ -0,0 +1,516 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use flate2::{Crc, read::DeflateDecoder};
+
+use crate::{encoding::NameEncoding, error::Error, limit, sort::SortMode};
+
+use super::{
+    Direction, File,
+    bytes::{malformed, u16_at, u32_at, u64_at},
+    chapter_of, group_chapters, is_page_name, method_name, peek_index,
+};
+
+const LOCAL_HEADER_SIGNATURE: &[u8] = b"PK\x03\x04";
+const LOCAL_HEADER_SIZE: usize = 30;
+const DESCRIPTOR_SIGNATURE: &[u8] = b"PK\x07\x08";
+// signature, crc, compressed and uncompressed size. sizes are 8 bytes each in zip64.
+const DESCRIPTOR_SIZE: usize = 16;
+const ZIP64_DESCRIPTOR_SIZE: usize = 24;
+const FLAG_ENCRYPTED: u16 = 1;
+const FLAG_DESCRIPTOR: u16 = 1 << 3;
+const FLAG_UTF8: u16 = 1 << 11;
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+// zip without a readable central directory. (a partial download mostly) entries are found by
+// walking local headers from the start of file and every page that decompresses correctly is
+// shown. what could be read of a damaged page is shown as broken image.
+pub(super) struct RecoveredZip<R> {
+    idx: usize,
+    ordered_names: Box<[Entry]>,
+    // folders inside archive as chapters. (name, index of it's first page)
+    chapters: Box<[(Box<str>, usize)]>,
+    reader: R,
+}
+
+struct Entry {
+    name: Box<str>,
+    data_start: u64,
+    compressed: u64,
+    size: u64,
+    crc: u32,
+    method: u16,
+    encrypted: bool,
+    // entry failed to decompress or it's checksum does not match when it's read.
+    damaged: bool,
+}
+
+impl<R> RecoveredZip<R>
+where
+    R: Read + Seek,
+{
+    // return None when no page can be found.
+    pub(super) fn try_new(mut reader: R, sort: SortMode) -> Result<Option<Self>, Error> {
+        let entries = scan(&mut reader)?;
+
+        let encoding = NameEncoding::detect(
+            entries
+                .iter()
+                .filter(|(name, utf8, _)| !utf8 && std::str::from_utf8(name).is_err())
+                .map(|(name, _, _)| name.as_slice()),
+        )
+        .encoding();
+
+        let ordered_names = entries
+            .into_iter()
+            .filter_map(|(raw, utf8, mut entry)| {
+                entry.name = match (std::str::from_utf8(&raw), encoding) {
+                    (Ok(name), _) => name.into(),
+                    (Err(_), Some(encoding)) if !utf8 => encoding.decode(&raw).0.into(),
+                    _ => String::from_utf8_lossy(&raw).into(),
+                };
+                is_page_name(&entry.name).then_some(entry)
+            })
+            .collect::<Box<[_]>>();
+
+        if ordered_names.is_empty() {
+            return Ok(None);
+        }
+
+        let mut this = Self {
+            idx: 0,
+            ordered_names,
+            chapters: Box::default(),
+            reader,
+        };
+        this.sort(sort);
+        Ok(Some(this))
+    }
+
+    fn read_by_index(&mut self, idx: usize, buf: &mut Vec<u8>) -> Result<(), Error> {
+        let Some(entry) = self.ordered_names.get_mut(idx) else {
+            return Ok(());
+        };
+
+        if entry.encrypted {
+            return Err(Error::Unsupported(format!(
+                "encrypted entry {} can not be recovered",
+                entry.name
+            )));
+        }
+
+        let limits = limit::limits();
+        limits.check_entry(&entry.name, entry.size, entry.compressed)?;
+
+        self.reader.seek(SeekFrom::Start(entry.data_start))?;
+        let data = (&mut self.reader).take(entry.compressed);
+
+        let len = buf.len();
+        let res = match entry.method {
+            STORED => limits.read_entry(&entry.name, data, buf),
+            DEFLATED => limits.read_entry(&entry.name, DeflateDecoder::new(data), buf),
+            method => {
+                return Err(Error::Unsupported(format!(
+                    "zip entry {} is compressed with unsupported method {}",
+                    entry.name,
+                    method_name(method)
+                )));
+            }
+        };
+
+        let mut crc = Crc::new();
+        crc.update(&buf[len..]);
+
+        entry.damaged = match res {
+            Ok(()) if crc.sum() == entry.crc => false,
+            // what could be decompressed is kept. it's rendered as a broken image when it can
+            // not be decoded.
+            Ok(()) | Err(Error::Io(_)) => true,
+            Err(e) => return Err(e),
+        };
+
+        if entry.damaged && buf.len() == len {
+            let msg = format!("page {} is damaged and none of it can be read", entry.name);
+            return Err(malformed("zip", &msg));
+        }
+
+        Ok(())
+    }
+}
+
+impl<R> File for RecoveredZip<R>
+where
+    R: Read + Seek,
+{
+    fn is_head(&self) -> bool {
+        self.idx == 0
+    }
+
+    fn is_eof(&self) -> bool {
+        self.idx == self.ordered_names.len().saturating_sub(1)
+    }
+
+    fn read(&mut self, buf: &mut Vec<u8>, direction: Direction) -> Result<(), Error> {
+        match direction {
+            Direction::Next if self.is_eof() => return Ok(()),
+            Direction::Prev if self.is_head() => return Ok(()),
+            Direction::Next => self.idx += 1,
+            Direction::Prev => self.idx -= 1,
+            Direction::First => self.idx = 0,
+            Direction::Last => self.idx = self.ordered_names.len().saturating_sub(1),
+            Direction::Offset(idx) => {
+                self.idx = idx.min(self.ordered_names.len().saturating_sub(1))
+            }
+            Direction::Chapter(n) => match self.chapters.get(n) {
+                Some((_, start)) => self.idx = *start,
+                None => return Ok(()),
+            },
+        }
+
+        self.read_by_index(self.idx, buf)
+    }
+
+    fn locate(&self, distance: isize, position: &mut Vec<usize>) -> bool {
+        peek_index(self.idx, self.ordered_names.len(), distance)
+            .map(|at| position.push(at))
+            .is_some()
+    }
+
+    fn peek(&mut self, buf: &mut Vec<u8>, distance: isize) -> Result<bool, Error> {
+        let Some(at) = peek_index(self.idx, self.ordered_names.len(), distance) else {
+            return Ok(false);
+        };
+        self.read_by_index(at, buf).map(|_| true)
+    }
+
+    fn sort(&mut self, sort: SortMode) {
+        self.ordered_names
+            .sort_by(|a, b| sort.cmp_name(&a.name, &b.name));
+        self.chapters = group_chapters(&mut self.ordered_names, |entry| &entry.name);
+        self.idx = 0;
+    }
+
+    fn chapters(&self) -> &[(Box<str>, usize)] {
+        &self.chapters
+    }
+
+    fn chapter(&self) -> Option<usize> {
+        chapter_of(&self.chapters, self.idx)
+    }
+
+    fn notice(&self) -> Option<String> {
+        match self.ordered_names.get(self.idx) {
+            Some(entry) if entry.damaged => Some(format!("page {} is damaged", entry.name)),
+            _ => Some(format!(
+                "zip is incomplete. {} pages are found",
+                self.ordered_names.len()
+            )),
+        }
+    }
+}
+
+// walk local headers from the start of file until one can not be read. entries are returned
+// with their raw names and if the names are flagged as utf-8. an entry cut off by the end of
+// file is the last one and it's kept so the part of it that is there can be shown.
+fn scan<R>(reader: &mut R) -> Result<Vec<(Vec<u8>, bool, Entry)>, Error>
+where
+    R: Read + Seek,
+{
+    let len = reader.seek(SeekFrom::End(0))?;
+    let mut pos = 0;
+    let mut entries = Vec::new();
+
+    loop {
+        reader.seek(SeekFrom::Start(pos))?;
+
+        let mut header = [0; LOCAL_HEADER_SIZE];
+        if reader.read_exact(&mut header).is_err() || !header.starts_with(LOCAL_HEADER_SIGNATURE) {
+            break;
+        }
+
+        let flags = u16_at(&header, 6);
+        let mut name = vec![0; u16_at(&header, 26) as usize];
+        let mut extra = vec![0; u16_at(&header, 28) as usize];
+        if reader.read_exact(&mut name).is_err() || reader.read_exact(&mut extra).is_err() {
+            break;
+        }
+
+        let mut entry = Entry {
+            name: Box::default(),
+            data_start: pos + (LOCAL_HEADER_SIZE + name.len() + extra.len()) as u64,
+            compressed: u32_at(&header, 18) as u64,
+            size: u32_at(&header, 22) as u64,
+            crc: u32_at(&header, 14),
+            method: u16_at(&header, 8),
+            encrypted: flags & FLAG_ENCRYPTED != 0,
+            damaged: false,
+        };
+
+        if entry.compressed == u32::MAX as u64 || entry.size == u32::MAX as u64 {
+            read_zip64_sizes(&extra, &mut entry);
+        }
+
+        let mut descriptor = 0;
+        if flags & FLAG_DESCRIPTOR != 0 {
+            match find_descriptor(reader, entry.data_start)? {
+                Some((crc, compressed, size, len)) => {
+                    entry.crc = crc;
+                    entry.compressed = compressed;
+                    entry.size = size;
+                    descriptor = len;
+                }
+                // the data runs to the end of file.
+                None => {
+                    entry.compressed = len - entry.data_start;
+                    entry.size = 0;
+                }
+            }
+        }
+
+        let end = entry.data_start + entry.compressed;
+        let utf8 = flags & FLAG_UTF8 != 0;
+        if end >= len || descriptor == 0 && flags & FLAG_DESCRIPTOR != 0 {
+            entry.compressed = entry.compressed.min(len - entry.data_start);
+            entries.push((name, utf8, entry));
+            break;
+        }
+        entries.push((name, utf8, entry));
+        pos = end + descriptor as u64;
+    }
+
+    Ok(entries)
+}
+
+fn read_zip64_sizes(extra: &[u8], entry: &mut Entry) {
+    let mut field = 0;
+    while field + 4 <= extra.len() {
+        let id = u16_at(extra, field);
+        let len = u16_at(extra, field + 2) as usize;
+        let data = &extra[field + 4..(field + 4 + len).min(extra.len())];
+        if id == 0x0001 {
+            if data.len() >= 8 {
+                entry.size = u64_at(data, 0);
+            }
+            if data.len() >= 16 {
+                entry.compressed = u64_at(data, 8);
+            }
+            return;
+        }
+        field += 4 + len;
+    }
+}
+
+// sizes of entry written with data descriptor are only known after it's data. the descriptor
+// is the first signature where the compressed size in it matches the bytes in front of it.
+// return crc, compressed size, uncompressed size and length of the descriptor.
+fn find_descriptor<R>(
+    reader: &mut R,
+    data_start: u64,
+) -> Result<Option<(u32, u64, u64, usize)>, Error>
+where
+    R: Read + Seek,
+{
+    reader.seek(SeekFrom::Start(data_start))?;
+
+    let mut chunk = vec![0; 64 * 1024];
+    // bytes read but not searched yet. base is their offset from data start.
+    let mut window = Vec::new();
+    let mut base = 0u64;
+
+    loop {
+        let n = reader.read(&mut chunk)?;
+        window.extend_from_slice(&chunk[..n]);
+
+        // a descriptor near the end of what's read can be told apart once enough bytes follow.
+        let need = if n == 0 {
+            DESCRIPTOR_SIZE
+        } else {
+            ZIP64_DESCRIPTOR_SIZE
+        };
+        let searched = (window.len() + 1).saturating_sub(need);
+
+        for p in 0..searched {
+            if !window[p..].starts_with(DESCRIPTOR_SIGNATURE) {
+                continue;
+            }
+            let compressed = base + p as u64;
+            let crc = u32_at(&window, p + 4);
+            // little endian size of zip64 descriptor matches the short one too. short one is
+            // followed by the next header.
+            if window.len() >= p + ZIP64_DESCRIPTOR_SIZE
+                && u64_at(&window, p + 8) == compressed
+                && !window[p + DESCRIPTOR_SIZE..].starts_with(b"PK")
+            {
+                let size = u64_at(&window, p + 16);
+                return Ok(Some((crc, compressed, size, ZIP64_DESCRIPTOR_SIZE)));
+            }
+            if u32_at(&window, p + 8) as u64 == compressed {
+                return Ok(Some((
+                    crc,
+                    compressed,
+                    u32_at(&window, p + 12) as u64,
+                    DESCRIPTOR_SIZE,
+                )));
+            }
+        }
+
+        if n == 0 {
+            return Ok(None);
+        }
+
+        window.drain(..searched);
+        base += searched as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn crc(data: &[u8]) -> u32 {
+        let mut crc = Crc::new();
+        crc.update(data);
+        crc.sum()
+    }
+
+    // local header and data of a stored entry. sizes and crc are left zero when they are in a
+    // data descriptor after it.
+    fn local(name: &str, data: &[u8], flags: u16, crc: u32) -> Vec<u8> {
+        let (crc, size) = if flags & FLAG_DESCRIPTOR != 0 {
+            (0, 0)
+        } else {
+            (crc, data.len() as u32)
+        };
+        let mut buf = LOCAL_HEADER_SIGNATURE.to_vec();
+        buf.extend_from_slice(&20u16.to_le_bytes());
+        buf.extend_from_slice(&flags.to_le_bytes());
+        buf.extend_from_slice(&STORED.to_le_bytes());
+        buf.extend_from_slice(&[0; 4]);
+        buf.extend_from_slice(&crc.to_le_bytes());
+        buf.extend_from_slice(&size.to_le_bytes());
+        buf.extend_from_slice(&size.to_le_bytes());
+        buf.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        buf.extend_from_slice(name.as_bytes());
+        buf.extend_from_slice(data);
+        buf
+    }
+
+    fn descriptor(data: &[u8]) -> Vec<u8> {
+        let mut buf = DESCRIPTOR_SIGNATURE.to_vec();
+        buf.extend_from_slice(&crc(data).to_le_bytes());
+        buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        buf
+    }
+
+    fn names(zip: &[u8]) -> Vec<(String, u64)> {
+        scan(&mut Cursor::new(zip))
+            .unwrap()
+            .into_iter()
+            .map(|(name, _, entry)| (String::from_utf8(name).unwrap(), entry.compressed))
+            .collect()
+    }
+
+    #[test]
+    fn entries_are_walked_until_end_of_file() {
+        let mut zip = local("001.png", b"first", 0, crc(b"first"));
+        zip.extend(local("002.png", b"second", 0, crc(b"second")));
+        assert_eq!(names(&zip), [("001.png".into(), 5), ("002.png".into(), 6)]);
+
+        // entry cut off by end of file is kept with what is there.
+        zip.truncate(zip.len() - 2);
+        assert_eq!(names(&zip), [("001.png".into(), 5), ("002.png".into(), 4)]);
+
+        // garbage after the last header ends the walk.
+        let mut zip = local("001.png", b"first", 0, crc(b"first"));
+        zip.extend_from_slice(b"PK\x01\x02 central directory");
+        assert_eq!(names(&zip), [("001.png".into(), 5)]);
+    }
+
+    #[test]
+    fn sizes_are_taken_from_descriptor() {
+        // data holds a signature whose size does not match the bytes in front of it.
+        let data = b"page PK\x07\x08\0\0\0\0\x03\0\0\0 data";
+        let mut zip = local("001.png", data, FLAG_DESCRIPTOR, 0);
+        zip.extend(descriptor(data));
+        zip.extend(local("002.png", b"second", 0, crc(b"second")));
+
+        let entries = scan(&mut Cursor::new(&zip)).unwrap();
+        let (_, _, entry) = &entries[0];
+        assert_eq!(entry.compressed, data.len() as u64);
+        assert_eq!(entry.crc, crc(data));
+        assert_eq!(entries.len(), 2);
+
+        let start = LOCAL_HEADER_SIZE as u64 + 7;
+        let found = find_descriptor(&mut Cursor::new(&zip), start).unwrap();
+        assert_eq!(
+            found,
+            Some((
+                crc(data),
+                data.len() as u64,
+                data.len() as u64,
+                DESCRIPTOR_SIZE
+            ))
+        );
+    }
+
+    #[test]
+    fn zip64_descriptor_is_found() {
+        let data = b"page";
+        let mut zip = local("001.png", data, FLAG_DESCRIPTOR, 0);
+        zip.extend_from_slice(DESCRIPTOR_SIGNATURE);
+        zip.extend_from_slice(&crc(data).to_le_bytes());
+        zip.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        zip.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        zip.extend(local("002.png", b"second", 0, crc(b"second")));
+        assert_eq!(names(&zip), [("001.png".into(), 4), ("002.png".into(), 6)]);
+
+        let start = LOCAL_HEADER_SIZE as u64 + 7;
+        let found = find_descriptor(&mut Cursor::new(&zip), start).unwrap();
+        assert_eq!(found, Some((crc(data), 4, 4, ZIP64_DESCRIPTOR_SIZE)));
+    }
+
+    #[test]
+    fn missing_descriptor_runs_to_end_of_file() {
+        let zip = local("001.png", b"cut off page", FLAG_DESCRIPTOR, 0);
+        let start = LOCAL_HEADER_SIZE as u64 + 7;
+        assert_eq!(
+            find_descriptor(&mut Cursor::new(&zip), start).unwrap(),
+            None
+        );
+        assert_eq!(names(&zip), [("001.png".into(), 12)]);
+    }
+
+    #[test]
+    fn damaged_page_is_noticed_only_when_shown() {
+        let mut zip = local("001.png", b"first", 0, crc(b"first"));
+        zip.extend(local("002.png", b"second", 0, 0));
+        zip.extend(local("003.png", b"", 0, 1));
+        zip.extend(local("004.png", b"fourth", 0, crc(b"fourth")));
+        let mut file = RecoveredZip::try_new(Cursor::new(zip), SortMode::Natural)
+            .unwrap()
+            .unwrap();
+        let incomplete = Some(String::from("zip is incomplete. 4 pages are found"));
+
+        let mut buf = Vec::new();
+        file.read(&mut buf, Direction::First).unwrap();
+        assert_eq!(buf, b"first");
+        assert!(file.peek(&mut Vec::new(), 1).unwrap());
+        assert_eq!(file.notice(), incomplete);
+
+        buf.clear();
+        file.read(&mut buf, Direction::Next).unwrap();
+        assert_eq!(buf, b"second");
+        assert_eq!(file.notice(), Some(String::from("page 002.png is damaged")));
+
+        // nothing of the page is there to be shown.
+        assert!(file.read(&mut Vec::new(), Direction::Next).is_err());
+        buf.clear();
+        file.read(&mut buf, Direction::Next).unwrap();
+        assert_eq!(buf, b"fourth");
+        assert_eq!(file.notice(), incomplete);
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    collections::VecDeque,
+    io::{Read, Seek},
+};
+
+use sevenz_rust2::{Archive, BlockDecoder, Password};
+
+use crate::{cancel, error::Error, limit, sort::SortMode};
+
+use super::{Direction, File, chapter_of, group_chapters, is_page_name, peek_index};
+
+// upper bound of decoded bytes kept from one block.
+const BLOCK_CACHE_LIMIT: usize = 256 * 1024 * 1024;
+
+// 7z archive packs entries into blocks. in a solid archive one block can hold many (or all)
+// entries and an entry can only be decoded after every entry in front of it in the same block.
+// decoded entries are cached per block so turning page inside a block does not decode it again.
+// decoding goes on past the page asked for until the cache is full so reading front to back
+// decodes a block once.
+pub(super) struct SevenZFile<R> {
+    idx: usize,
+    // name of entry and it's file index inside archive.
+    ordered_names: Box<[(Box<str>, usize)]>,
+    // folders inside archive as chapters. (name, index of it's first page)
+    chapters: Box<[(Box<str>, usize)]>,
+    archive: Archive,
+    password: Password,
+    source: R,
+    cache: BlockCache,
+}
+
+#[derive(Default)]
+struct BlockCache {
+    block: Option<usize>,
+    // continuous run of decoded entries. key is the file index inside archive.
+    entries: VecDeque<(usize, Vec<u8>)>,
+    size: usize,
+}
+
+impl BlockCache {
+    fn get(&self, block: usize, file: usize) -> Option<&[u8]> {
+        if self.block != Some(block) {
+            return None;
+        }
+        let (first, _) = self.entries.front()?;
+        self.entries
+            .get(file.checked_sub(*first)?)
+            .map(|(_, data)| data.as_slice())
+    }
+
+    fn reset(&mut self, block: usize) {
+        self.block = Some(block);
+        self.entries.clear();
+        self.size = 0;
+    }
+
+    // push decoded entry and return false when decoding should stop. it stops once the cache is
+    // full past the target.
+    fn push(&mut self, file: usize, data: Vec<u8>, target: usize) -> bool {
+        self.size += data.len();
+        self.entries.push_back((file, data));
+
+        // drop entries in front of target first. they are the least likely to be read again.
+        while self.size > BLOCK_CACHE_LIMIT {
+            match self.entries.front() {
+                Some((f, _)) if *f < target => {
+                    let (_, data) = self.entries.pop_front().unwrap();
+                    self.size -= data.len();
+                }
+                _ => break,
+            }
+        }
+
+        file < target || self.size <= BLOCK_CACHE_LIMIT
+    }
+}
+
+impl<R> SevenZFile<R>
+where
+    R: Read + Seek,
+{
+    pub(super) fn try_new(mut source: R, sort: SortMode) -> Result<Self, Error> {
+        let password = Password::empty();
+        let archive = Archive::read(&mut source, &password)?;
+
+        let ordered_names = archive
+            .files
+            .iter()
+            .enumerate()
+            .filter(|(_, file)| !file.is_directory && file.has_stream && is_page_name(&file.name))
+            .map(|(idx, file)| (Box::from(file.name.as_str()), idx))
+            .collect();
+
+        let mut this = Self {
+            idx: 0,
+            ordered_names,
+            chapters: Box::default(),
+            archive,
+            password,
+            source,
+            cache: BlockCache::default(),
+        };
+        this.sort(sort);
+        Ok(this)
+    }
+
+    fn read_by_index(&mut self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        let Some(&(_, file)) = self.ordered_names.get(self.idx) else {
+            return Ok(());
+        };
+        let Some(block) = self.archive.stream_map.file_block_index[file] else {
+            return Ok(());
+        };
+
+        if let Some(data) = self.cache.get(block, file) {
+            buf.extend_from_slice(data);
+            return Ok(());
+        }
+
+        self.cache.reset(block);
+
+        let first = self.archive.stream_map.block_first_file_index[block];
+        let mut pos = first;
+        // packed size of block is told by it's first entry.
+        let packed = self.archive.files[first].compressed_size;
+        let unpacked = self.archive.blocks[block].get_unpack_size();
+        let cache = &mut self.cache;
+        let limits = limit::limits();
+        let mut exceeded = None;
+
+        BlockDecoder::new(1, block, &self.archive, &self.password, &mut self.source)
+            .for_each_entries(&mut |entry, reader| {
+                // entry over the limit stops decoding and it's error is returned after.
+                if let Err(e) = limits.check_packed_entry(&entry.name, entry.size, unpacked, packed)
+                {
+                    exceeded = Some(e);
+                    return Ok(false);
+                }
+                let mut data = Vec::with_capacity(entry.size as usize);
+                cancel::Reader(reader).read_to_end(&mut data)?;
+                let cont = cache.push(pos, data, file);
+                pos += 1;
+                Ok(cont)
+            })?;
+
+        if let Some(e) = exceeded {
+            return Err(e);
+        }
+
+        if let Some(data) = self.cache.get(block, file) {
+            buf.extend_from_slice(data);
+        }
+
+        Ok(())
+    }
+}
+
+impl<R> File for SevenZFile<R>
+where
+    R: Read + Seek,
+{
+    fn is_head(&self) -> bool {
+        self.idx == 0
+    }
+
+    fn is_eof(&self) -> bool {
+        self.idx == self.ordered_names.len().saturating_sub(1)
+    }
+
+    fn read(&mut self, buf: &mut Vec<u8>, direction: Direction) -> Result<(), Error> {
+        match direction {
+            Direction::Next if self.is_eof() => return Ok(()),
+            Direction::Prev if self.is_head() => return Ok(()),
+            Direction::Next => self.idx += 1,
+            Direction::Prev => self.idx -= 1,
+            Direction::First => self.idx = 0,
+            Direction::Last => self.idx = self.ordered_names.len().saturating_sub(1),
+            Direction::Offset(idx) => {
+                self.idx = idx.min(self.ordered_names.len().saturating_sub(1))
+            }
+            Direction::Chapter(n) => match self.chapters.get(n) {
+                Some((_, start)) => self.idx = *start,
+                None => return Ok(()),
+            },
+        }
+
+        self.read_by_index(buf)
+    }
+
+    fn locate(&self, distance: isize, position: &mut Vec<usize>) -> bool {
+        peek_index(self.idx, self.ordered_names.len(), distance)
+            .map(|at| position.push(at))
+            .is_some()
+    }
+
+    fn peek(&mut self, buf: &mut Vec<u8>, distance: isize) -> Result<bool, Error> {
+        let Some(at) = peek_index(self.idx, self.ordered_names.len(), distance) else {
+            return Ok(false);
+        };
+        let idx = std::mem::replace(&mut self.idx, at);
+        let res = self.read_by_index(buf);
+        self.idx = idx;
+        res.map(|_| true)
+    }
+
+    fn sort(&mut self, sort: SortMode) {
+        self.ordered_names
+            .sort_by(|(a, _), (b, _)| sort.cmp_name(a, b));
+        self.chapters = group_chapters(&mut self.ordered_names, |(name, _)| name);
+        self.idx = 0;
+    }
+
+    fn chapters(&self) -> &[(Box<str>, usize)] {
+        &self.chapters
+    }
+
+    fn chapter(&self) -> Option<usize> {
+        chapter_of(&self.chapters, self.idx)
+    }
+}
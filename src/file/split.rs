@@ -0,0 +1,619 @@
+use std::{
+    fs,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use crate::{error::Error, limit};
+
+use super::{
+    bytes::{malformed, u16_at, u32_at, u64_at},
+    format::Format,
+};
+
+const EOCD_SIGNATURE: &[u8] = b"PK\x05\x06";
+const EOCD_SIZE: usize = 22;
+const ZIP64_LOCATOR_SIGNATURE: &[u8] = b"PK\x06\x07";
+const ZIP64_LOCATOR_SIZE: usize = 20;
+const ZIP64_EOCD_SIGNATURE: &[u8] = b"PK\x06\x06";
+const ZIP64_EOCD_SIZE: usize = 56;
+const CD_SIGNATURE: &[u8] = b"PK\x01\x02";
+const CD_SIZE: usize = 46;
+// end of central directory is in the last 64KiB comment plus the record itself.
+const EOCD_SEARCH: u64 = EOCD_SIZE as u64 + u16::MAX as u64;
+
+// an archive split into parts. spanned set is name.z01, name.z02 .. name.zip where offsets in
+// central directory are relative to each part. numbered set is an archive cut into pieces as
+// name.zip.001, name.zip.002 .. and the pieces joined together are the whole archive.
+enum Set {
+    Spanned { stem: PathBuf, upper: bool },
+    Numbered { stem: PathBuf, width: usize },
+}
+
+impl Set {
+    fn detect(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?;
+        let stem = path.with_extension("");
+
+        if ext.len() >= 3 && ext.bytes().all(|b| b.is_ascii_digit()) {
+            let this = Self::Numbered {
+                stem,
+                width: ext.len(),
+            };
+            // numbered files that are not pieces of an archive (page.001, page.002 ..) are left
+            // alone.
+            return this.format().map(|_| this);
+        }
+
+        let upper = ext.bytes().any(|b| b.is_ascii_uppercase());
+        let is_part = ext.len() >= 3
+            && ext.as_bytes()[0].eq_ignore_ascii_case(&b'z')
+            && ext.bytes().skip(1).all(|b| b.is_ascii_digit());
+
+        let this = Self::Spanned { stem, upper };
+        (is_part || (ext.eq_ignore_ascii_case("zip") && this.part(1).exists())).then_some(this)
+    }
+
+    // path of part with given number starting from 1. spanned set ends with the zip itself
+    // and it's number is 0.
+    fn part(&self, n: usize) -> PathBuf {
+        let ext = match *self {
+            Self::Spanned { upper, .. } if n == 0 => if upper { "ZIP" } else { "zip" }.into(),
+            Self::Spanned { upper, .. } => format!("{}{n:02}", if upper { 'Z' } else { 'z' }),
+            Self::Numbered { width, .. } => format!("{n:0width$}"),
+        };
+        let stem = match self {
+            Self::Spanned { stem, .. } | Self::Numbered { stem, .. } => stem,
+        };
+        let mut name = stem.clone().into_os_string();
+        name.push(".");
+        name.push(ext);
+        PathBuf::from(name)
+    }
+
+    // format of the joined parts told by the leading bytes of the first one. None when they
+    // are not an archive that can be read from joined parts.
+    fn format(&self) -> Option<Format> {
+        match self {
+            Self::Spanned { .. } => Some(Format::Zip),
+            Self::Numbered { .. } => match Format::detect(&self.part(1)).ok()?? {
+                format @ (Format::Zip | Format::SevenZ | Format::Tar) => Some(format),
+                _ => None,
+            },
+        }
+    }
+
+    // the part standing for the whole set when listing files.
+    fn leading(&self) -> PathBuf {
+        match self {
+            Self::Spanned { .. } => self.part(0),
+            Self::Numbered { .. } => self.part(1),
+        }
+    }
+}
+
+// the part standing for the whole split set given path belongs to. None when path is not a
+// part of split zip.
+pub(super) fn leading_part(path: &Path) -> Option<PathBuf> {
+    Set::detect(path).map(|set| set.leading())
+}
+
+// part of split zip that is not the leading one. it's read through the leading part.
+pub(super) fn is_trailing_part(path: &Path) -> bool {
+    leading_part(path).is_some_and(|p| p != path)
+}
+
+// open every part of split archive given path belongs to as one reader and tell the format of
+// it. None when path is not part of a split archive.
+pub(super) fn try_open(path: &Path) -> Result<Option<(Format, SplitReader)>, Error> {
+    let Some(set) = Set::detect(path) else {
+        return Ok(None);
+    };
+    let Some(format) = set.format() else {
+        return Ok(None);
+    };
+    let reader = match set {
+        Set::Spanned { .. } => open_spanned(&set)?,
+        Set::Numbered { .. } => open_numbered(&set, format)?,
+    };
+    Ok(Some((format, reader)))
+}
+
+fn open_numbered(set: &Set, format: Format) -> Result<SplitReader, Error> {
+    let mut parts = vec![set.part(1)];
+    loop {
+        let next = set.part(parts.len() + 1);
+        if !next.exists() {
+            break;
+        }
+        parts.push(next);
+    }
+
+    let mut reader = SplitReader::try_new(&parts)?;
+    // pieces at the end of zip are gone when the end of central directory can not be found.
+    if format == Format::Zip && find_eocd(&mut reader)?.is_none() {
+        return Err(missing(&set.part(parts.len() + 1)));
+    }
+    Ok(reader)
+}
+
+fn open_spanned(set: &Set) -> Result<SplitReader, Error> {
+    let last = set.part(0);
+    let mut file = open_part(&last)?;
+    let (eocd_pos, eocd) = find_eocd(&mut file)?
+        .ok_or_else(|| malformed("split zip", "no end of central directory"))?;
+
+    // the last part tells how many parts are in front of it.
+    let count = u16_at(&eocd, 4) as usize;
+    let mut parts = (1..=count).map(|n| set.part(n)).collect::<Vec<_>>();
+    if let Some(part) = parts.iter().find(|p| !p.exists()) {
+        return Err(missing(part));
+    }
+    parts.push(last);
+
+    let mut reader = SplitReader::try_new(&parts)?;
+    let last_start = reader.starts[count];
+
+    let mut cd = Directory {
+        disk: u16_at(&eocd, 6) as u64,
+        entries: u16_at(&eocd, 10) as u64,
+        size: u32_at(&eocd, 12) as u64,
+        offset: u32_at(&eocd, 16) as u64,
+    };
+
+    if eocd_pos >= ZIP64_LOCATOR_SIZE as u64 {
+        let mut locator = [0; ZIP64_LOCATOR_SIZE];
+        reader.seek(SeekFrom::Start(
+            last_start + eocd_pos - ZIP64_LOCATOR_SIZE as u64,
+        ))?;
+        reader.read_exact(&mut locator)?;
+        if locator.starts_with(ZIP64_LOCATOR_SIGNATURE) {
+            let disk = u32_at(&locator, 4) as usize;
+            let start = reader.start_of(disk)?;
+            let mut record = [0; ZIP64_EOCD_SIZE];
+            reader.seek(SeekFrom::Start(start + u64_at(&locator, 8)))?;
+            reader.read_exact(&mut record)?;
+            if !record.starts_with(ZIP64_EOCD_SIGNATURE) {
+                return Err(malformed("split zip", "bad zip64 end of central directory"));
+            }
+            cd = Directory {
+                disk: u32_at(&record, 20) as u64,
+                entries: u64_at(&record, 32),
+                size: u64_at(&record, 40),
+                offset: u64_at(&record, 48),
+            };
+        }
+    }
+
+    let cd_start = reader.start_of(cd.disk as usize)? + cd.offset;
+    if cd_start
+        .checked_add(cd.size)
+        .is_none_or(|end| end > reader.len)
+    {
+        return Err(malformed("split zip", "central directory is out of set"));
+    }
+    limit::limits().check_entry("central directory", cd.size, cd.size)?;
+    let mut entries = vec![0; cd.size as usize];
+    reader.seek(SeekFrom::Start(cd_start))?;
+    reader.read_exact(&mut entries)?;
+
+    let mut tail = rebase_entries(&reader, entries)?;
+    let cd_size = tail.len() as u64;
+    write_eocd(&mut tail, cd.entries, cd_size, cd_start);
+
+    reader.len = cd_start;
+    reader.tail = tail;
+    reader.rewind()?;
+    Ok(reader)
+}
+
+struct Directory {
+    disk: u64,
+    entries: u64,
+    size: u64,
+    offset: u64,
+}
+
+// point every entry of central directory to the position of it's local header in joined parts.
+fn rebase_entries(reader: &SplitReader, mut entries: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let mut pos = 0;
+    while pos < entries.len() {
+        let entry = entries
+            .get(pos..pos + CD_SIZE)
+            .filter(|e| e.starts_with(CD_SIGNATURE))
+            .ok_or_else(|| malformed("split zip", "bad central directory entry"))?;
+
+        let name_len = u16_at(entry, 28) as usize;
+        let extra_len = u16_at(entry, 30) as usize;
+        let comment_len = u16_at(entry, 32) as usize;
+        let mut disk = u16_at(entry, 34) as u64;
+        let mut offset = u32_at(entry, 42) as u64;
+
+        // zip64 extra field holds the values header can not. they are in fixed order and only
+        // present when header value is saturated.
+        let extra = pos + CD_SIZE + name_len;
+        let mut offset_at = None;
+        let mut disk_at = None;
+        let mut field = extra;
+        while field + 4 <= extra + extra_len && field + 4 <= entries.len() {
+            let id = u16_at(&entries, field);
+            let len = u16_at(&entries, field + 2) as usize;
+            if id == 0x0001 {
+                // values must be inside the field and the field inside central directory.
+                let end = (field + 4 + len).min(entries.len());
+                let short = || malformed("split zip", "bad zip64 extra field");
+                let mut at = field + 4;
+                for value in [u32_at(entry, 24), u32_at(entry, 20)] {
+                    if value == u32::MAX {
+                        at += 8;
+                    }
+                }
+                if offset == u32::MAX as u64 {
+                    if at + 8 > end {
+                        return Err(short());
+                    }
+                    offset = u64_at(&entries, at);
+                    offset_at = Some(at);
+                    at += 8;
+                }
+                if disk == u16::MAX as u64 {
+                    if at + 4 > end {
+                        return Err(short());
+                    }
+                    disk = u32_at(&entries, at) as u64;
+                    disk_at = Some(at);
+                }
+                break;
+            }
+            field += 4 + len;
+        }
+
+        let offset = reader.start_of(disk as usize)? + offset;
+        match offset_at {
+            Some(at) => entries[at..at + 8].copy_from_slice(&offset.to_le_bytes()),
+            None => {
+                let offset = u32::try_from(offset).map_err(|_| {
+                    Error::Unsupported("split zip over 4GiB without zip64 record".into())
+                })?;
+                entries[pos + 42..pos + 46].copy_from_slice(&offset.to_le_bytes());
+            }
+        }
+        match disk_at {
+            Some(at) => entries[at..at + 4].copy_from_slice(&0u32.to_le_bytes()),
+            None => entries[pos + 34..pos + 36].copy_from_slice(&0u16.to_le_bytes()),
+        }
+
+        pos += CD_SIZE + name_len + extra_len + comment_len;
+    }
+    Ok(entries)
+}
+
+// end of central directory of a single part zip. zip64 records are written when the values
+// do not fit the classic one.
+fn write_eocd(buf: &mut Vec<u8>, entries: u64, size: u64, offset: u64) {
+    let zip64 = entries >= u16::MAX as u64 || size >= u32::MAX as u64 || offset >= u32::MAX as u64;
+
+    if zip64 {
+        let record = offset + size;
+        buf.extend_from_slice(ZIP64_EOCD_SIGNATURE);
+        buf.extend_from_slice(&(ZIP64_EOCD_SIZE as u64 - 12).to_le_bytes());
+        buf.extend_from_slice(&45u16.to_le_bytes());
+        buf.extend_from_slice(&45u16.to_le_bytes());
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.extend_from_slice(&entries.to_le_bytes());
+        buf.extend_from_slice(&entries.to_le_bytes());
+        buf.extend_from_slice(&size.to_le_bytes());
+        buf.extend_from_slice(&offset.to_le_bytes());
+
+        buf.extend_from_slice(ZIP64_LOCATOR_SIGNATURE);
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.extend_from_slice(&record.to_le_bytes());
+        buf.extend_from_slice(&1u32.to_le_bytes());
+    }
+
+    let entries = u16::try_from(entries).unwrap_or(u16::MAX);
+    buf.extend_from_slice(EOCD_SIGNATURE);
+    buf.extend_from_slice(&0u16.to_le_bytes());
+    buf.extend_from_slice(&0u16.to_le_bytes());
+    buf.extend_from_slice(&entries.to_le_bytes());
+    buf.extend_from_slice(&entries.to_le_bytes());
+    buf.extend_from_slice(&u32::try_from(size).unwrap_or(u32::MAX).to_le_bytes());
+    buf.extend_from_slice(&u32::try_from(offset).unwrap_or(u32::MAX).to_le_bytes());
+    buf.extend_from_slice(&0u16.to_le_bytes());
+}
+
+// position and content of end of central directory record.
+fn find_eocd<R>(reader: &mut R) -> Result<Option<(u64, [u8; EOCD_SIZE])>, Error>
+where
+    R: Read + Seek,
+{
+    let len = reader.seek(SeekFrom::End(0))?;
+    let start = len.saturating_sub(EOCD_SEARCH);
+    reader.seek(SeekFrom::Start(start))?;
+    let mut tail = Vec::with_capacity((len - start) as usize);
+    reader.read_to_end(&mut tail)?;
+
+    let found = tail
+        .windows(EOCD_SIGNATURE.len())
+        .enumerate()
+        .rev()
+        .find(|(pos, w)| *w == EOCD_SIGNATURE && pos + EOCD_SIZE <= tail.len())
+        .map(|(pos, _)| {
+            let mut eocd = [0; EOCD_SIZE];
+            eocd.copy_from_slice(&tail[pos..pos + EOCD_SIZE]);
+            (start + pos as u64, eocd)
+        });
+    Ok(found)
+}
+
+fn open_part(path: &Path) -> Result<fs::File, Error> {
+    fs::File::open(path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => missing(path),
+        _ => Error::Io(e),
+    })
+}
+
+fn missing(path: &Path) -> Error {
+    let name = path
+        .file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy();
+    Error::Io(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("split zip is incomplete. part {name} is missing"),
+    ))
+}
+
+// parts joined together as one file. tail is read after the first len bytes of parts and it's
+// where rewritten central directory of spanned set lives.
+pub(super) struct SplitReader {
+    parts: Box<[fs::File]>,
+    // position of every part in joined file. the last one is the end of all parts.
+    starts: Box<[u64]>,
+    len: u64,
+    tail: Vec<u8>,
+    pos: u64,
+}
+
+impl SplitReader {
+    fn try_new(paths: &[PathBuf]) -> Result<Self, Error> {
+        let mut parts = Vec::with_capacity(paths.len());
+        let mut starts = Vec::with_capacity(paths.len() + 1);
+        let mut len = 0;
+        for path in paths {
+            let file = open_part(path)?;
+            starts.push(len);
+            len += file.metadata()?.len();
+            parts.push(file);
+        }
+        starts.push(len);
+
+        Ok(Self {
+            parts: parts.into_boxed_slice(),
+            starts: starts.into_boxed_slice(),
+            len,
+            tail: Vec::new(),
+            pos: 0,
+        })
+    }
+
+    fn start_of(&self, part: usize) -> Result<u64, Error> {
+        self.starts[..self.parts.len()]
+            .get(part)
+            .copied()
+            .ok_or_else(|| malformed("split zip", "entry points to a part out of set"))
+    }
+}
+
+impl Read for SplitReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.len {
+            let pos = (self.pos - self.len) as usize;
+            let n = (&self.tail[pos.min(self.tail.len())..]).read(buf)?;
+            self.pos += n as u64;
+            return Ok(n);
+        }
+
+        let part = self.starts.partition_point(|start| *start <= self.pos) - 1;
+        let end = self.starts[part + 1].min(self.len);
+        let max = buf.len().min((end - self.pos) as usize);
+
+        let file = &mut self.parts[part];
+        file.seek(SeekFrom::Start(self.pos - self.starts[part]))?;
+        let n = file.read(&mut buf[..max])?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for SplitReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let end = self.len + self.tail.len() as u64;
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(off) => end.checked_add_signed(off),
+            SeekFrom::Current(off) => self.pos.checked_add_signed(off),
+        };
+        self.pos = pos.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "seek to negative position")
+        })?;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::Crc;
+    use zip::ZipArchive;
+
+    use super::*;
+
+    // empty folder of given name under temp dir.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("shin-split-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn crc(data: &[u8]) -> u32 {
+        let mut crc = Crc::new();
+        crc.update(data);
+        crc.sum()
+    }
+
+    // local header and data of a stored entry.
+    fn local(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut buf = b"PK\x03\x04".to_vec();
+        buf.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        buf.extend_from_slice(&crc(data).to_le_bytes());
+        buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        buf.extend_from_slice(name.as_bytes());
+        buf.extend_from_slice(data);
+        buf
+    }
+
+    // central directory entry of a stored entry.
+    fn central(name: &str, data: &[u8], disk: u16, offset: u32, extra: &[u8]) -> Vec<u8> {
+        let mut buf = b"PK\x01\x02".to_vec();
+        buf.extend_from_slice(&[20, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        buf.extend_from_slice(&crc(data).to_le_bytes());
+        buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        buf.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+        buf.extend_from_slice(&0u16.to_le_bytes());
+        buf.extend_from_slice(&disk.to_le_bytes());
+        buf.extend_from_slice(&[0; 6]);
+        buf.extend_from_slice(&offset.to_le_bytes());
+        buf.extend_from_slice(name.as_bytes());
+        buf.extend_from_slice(extra);
+        buf
+    }
+
+    fn empty_reader() -> SplitReader {
+        SplitReader {
+            parts: Box::default(),
+            starts: Box::new([0]),
+            len: 0,
+            tail: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    #[test]
+    fn spanned_set_is_joined() {
+        let dir = temp_dir("spanned");
+        let first = local("001.png", b"first page");
+        let second = local("002.png", b"second page");
+
+        let mut cd = central("001.png", b"first page", 0, 0, &[]);
+        cd.extend(central("002.png", b"second page", 1, 0, &[]));
+
+        let mut last = second.clone();
+        let mut eocd = Vec::new();
+        write_eocd(&mut eocd, 2, cd.len() as u64, last.len() as u64);
+        // the last part is disk 1 and holds the central directory.
+        eocd[4..6].copy_from_slice(&1u16.to_le_bytes());
+        eocd[6..8].copy_from_slice(&1u16.to_le_bytes());
+        last.extend(cd);
+        last.extend(eocd);
+
+        fs::write(dir.join("book.z01"), first).unwrap();
+        fs::write(dir.join("book.zip"), last).unwrap();
+
+        assert_eq!(
+            leading_part(&dir.join("book.z01")),
+            Some(dir.join("book.zip"))
+        );
+        assert!(is_trailing_part(&dir.join("book.z01")));
+
+        let (format, reader) = try_open(&dir.join("book.zip")).unwrap().unwrap();
+        assert!(format == Format::Zip);
+        let mut zip = ZipArchive::new(reader).unwrap();
+        for (name, data) in [("001.png", "first page"), ("002.png", "second page")] {
+            let mut buf = String::new();
+            zip.by_name(name).unwrap().read_to_string(&mut buf).unwrap();
+            assert_eq!(buf, data);
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn numbered_pieces_of_archive_are_a_set() {
+        let dir = temp_dir("numbered");
+        let zip = local("001.png", b"page");
+        fs::write(dir.join("book.zip.001"), &zip[..10]).unwrap();
+        fs::write(dir.join("book.zip.002"), &zip[10..]).unwrap();
+
+        assert_eq!(
+            leading_part(&dir.join("book.zip.002")),
+            Some(dir.join("book.zip.001"))
+        );
+        assert!(is_trailing_part(&dir.join("book.zip.002")));
+        assert!(!is_trailing_part(&dir.join("book.zip.001")));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn numbered_files_of_no_archive_are_left_alone() {
+        let dir = temp_dir("plain");
+        fs::write(dir.join("page.001"), "not an archive").unwrap();
+        fs::write(dir.join("page.002"), "not an archive").unwrap();
+
+        assert_eq!(leading_part(&dir.join("page.002")), None);
+        assert!(!is_trailing_part(&dir.join("page.002")));
+        assert!(try_open(&dir.join("page.001")).unwrap().is_none());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rebased_zip64_offset_stays_in_extra_field() {
+        let mut extra = 0x0001u16.to_le_bytes().to_vec();
+        extra.extend_from_slice(&8u16.to_le_bytes());
+        extra.extend_from_slice(&7u64.to_le_bytes());
+        let entries = central("001.png", b"page", 0, u32::MAX, &extra);
+        let at = entries.len() - 8;
+
+        let mut reader = empty_reader();
+        reader.starts = Box::new([100, 200]);
+        // start_of only needs the number of parts.
+        reader.parts = vec![fs::File::open(std::env::current_exe().unwrap()).unwrap()].into();
+
+        let entries = rebase_entries(&reader, entries).unwrap();
+        assert_eq!(u64_at(&entries, at), 107);
+        assert_eq!(u32_at(&entries, 42), u32::MAX);
+    }
+
+    #[test]
+    fn short_zip64_extra_field_is_malformed() {
+        // offset is saturated but the field has no room for it.
+        let mut extra = 0x0001u16.to_le_bytes().to_vec();
+        extra.extend_from_slice(&4u16.to_le_bytes());
+        extra.extend_from_slice(&[0; 4]);
+        let entries = central("001.png", b"page", 0, u32::MAX, &extra);
+        assert!(rebase_entries(&empty_reader(), entries).is_err());
+
+        // field claims more bytes than central directory has.
+        let mut extra = 0x0001u16.to_le_bytes().to_vec();
+        extra.extend_from_slice(&8u16.to_le_bytes());
+        let mut entries = central("001.png", b"page", 0, u32::MAX, &extra);
+        entries[30..32].copy_from_slice(&12u16.to_le_bytes());
+        assert!(rebase_entries(&empty_reader(), entries).is_err());
+    }
+
+    #[test]
+    fn truncated_entry_is_malformed() {
+        let entries = central("001.png", b"page", 0, 0, &[]);
+        assert!(rebase_entries(&empty_reader(), entries[..CD_SIZE - 1].to_vec()).is_err());
+    }
+}
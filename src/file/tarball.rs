@@ -0,0 +1,202 @@
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+
+use flate2::read::GzDecoder;
+use ruzstd::decoding::StreamingDecoder;
+use tar::Archive;
+
+use crate::{error::Error, limit, sort::SortMode};
+
+use super::{Direction, File, chapter_of, group_chapters, is_page_name, peek_index};
+
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xB5, 0x2F, 0xFD];
+
+const USTAR_MAGIC: &[u8] = b"ustar";
+const USTAR_OFFSET: usize = 257;
+
+// check if bytes are the start of a plain or compressed tar archive. compressed stream is
+// decoded as far as bytes go and it's a tarball only when a tar header is found in it.
+pub(super) fn is_tar(bytes: &[u8]) -> bool {
+    if bytes.starts_with(GZIP_MAGIC) {
+        has_tar_header(GzDecoder::new(bytes))
+    } else if bytes.starts_with(ZSTD_MAGIC) {
+        StreamingDecoder::new(bytes).is_ok_and(has_tar_header)
+    } else {
+        is_tar_header(bytes)
+    }
+}
+
+fn has_tar_header(reader: impl Read) -> bool {
+    let mut head = Vec::new();
+    // bytes can end in the middle of stream. what's decoded before that is enough.
+    let _ = reader
+        .take((USTAR_OFFSET + USTAR_MAGIC.len()) as u64)
+        .read_to_end(&mut head);
+    is_tar_header(&head)
+}
+
+fn is_tar_header(bytes: &[u8]) -> bool {
+    bytes
+        .get(USTAR_OFFSET..)
+        .is_some_and(|b| b.starts_with(USTAR_MAGIC))
+}
+
+// open tar archive from reader. compressed tarball is decompressed into memory first so the
+// entries inside it can be seeked to.
+pub(super) fn try_open<R>(mut reader: R, sort: SortMode) -> Result<Box<dyn File>, Error>
+where
+    R: Read + Seek + 'static,
+{
+    let mut magic = [0; 4];
+    let n = reader.read(&mut magic)?;
+    reader.rewind()?;
+    let magic = &magic[..n];
+
+    let limits = limit::limits();
+    // whole compressed stream is one entry for it's compression ratio.
+    let compressed = reader.seek(SeekFrom::End(0))?;
+    reader.rewind()?;
+
+    if magic.starts_with(GZIP_MAGIC) {
+        let mut buf = Vec::new();
+        limits.read_entry("tarball", GzDecoder::new(reader), &mut buf)?;
+        limits.check_entry("tarball", buf.len() as u64, compressed)?;
+        Ok(Box::new(TarFile::try_new(Cursor::new(buf), sort)?) as _)
+    } else if magic.starts_with(ZSTD_MAGIC) {
+        let mut buf = Vec::new();
+        let decoder = StreamingDecoder::new(reader).map_err(io::Error::other)?;
+        limits.read_entry("tarball", decoder, &mut buf)?;
+        limits.check_entry("tarball", buf.len() as u64, compressed)?;
+        Ok(Box::new(TarFile::try_new(Cursor::new(buf), sort)?) as _)
+    } else {
+        Ok(Box::new(TarFile::try_new(reader, sort)?) as _)
+    }
+}
+
+// entries are indexed once when opened. every read after is a seek to the data of entry.
+struct TarFile<R> {
+    idx: usize,
+    ordered_names: Box<[TarEntry]>,
+    // folders inside archive as chapters. (name, index of it's first page)
+    chapters: Box<[(Box<str>, usize)]>,
+    reader: R,
+}
+
+struct TarEntry {
+    name: Box<str>,
+    pos: u64,
+    size: u64,
+}
+
+impl<R> TarFile<R>
+where
+    R: Read + Seek,
+{
+    fn try_new(reader: R, sort: SortMode) -> Result<Self, Error> {
+        let mut archive = Archive::new(reader);
+        let mut ordered_names = Vec::new();
+
+        for entry in archive.entries_with_seek()? {
+            let entry = entry?;
+            if !entry.header().entry_type().is_file() || entry.size() == 0 {
+                continue;
+            }
+            let name = entry.path()?.to_string_lossy().replace('\\', "/");
+            if !is_page_name(&name) {
+                continue;
+            }
+            ordered_names.push(TarEntry {
+                name: name.into_boxed_str(),
+                pos: entry.raw_file_position(),
+                size: entry.size(),
+            });
+        }
+
+        let mut this = Self {
+            idx: 0,
+            ordered_names: ordered_names.into_boxed_slice(),
+            chapters: Box::default(),
+            reader: archive.into_inner(),
+        };
+        this.sort(sort);
+        Ok(this)
+    }
+
+    fn read_by_index(&mut self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        let Some(entry) = self.ordered_names.get(self.idx) else {
+            return Ok(());
+        };
+
+        // entries of tar are stored as they are.
+        limit::limits().check_entry(&entry.name, entry.size, entry.size)?;
+        self.reader.seek(SeekFrom::Start(entry.pos))?;
+        buf.reserve(entry.size as usize);
+        (&mut self.reader).take(entry.size).read_to_end(buf)?;
+
+        Ok(())
+    }
+}
+
+impl<R> File for TarFile<R>
+where
+    R: Read + Seek,
+{
+    fn is_head(&self) -> bool {
+        self.idx == 0
+    }
+
+    fn is_eof(&self) -> bool {
+        self.idx == self.ordered_names.len().saturating_sub(1)
+    }
+
+    fn read(&mut self, buf: &mut Vec<u8>, direction: Direction) -> Result<(), Error> {
+        match direction {
+            Direction::Next if self.is_eof() => return Ok(()),
+            Direction::Prev if self.is_head() => return Ok(()),
+            Direction::Next => self.idx += 1,
+            Direction::Prev => self.idx -= 1,
+            Direction::First => self.idx = 0,
+            Direction::Last => self.idx = self.ordered_names.len().saturating_sub(1),
+            Direction::Offset(idx) => {
+                self.idx = idx.min(self.ordered_names.len().saturating_sub(1))
+            }
+            Direction::Chapter(n) => match self.chapters.get(n) {
+                Some((_, start)) => self.idx = *start,
+                None => return Ok(()),
+            },
+        }
+
+        self.read_by_index(buf)
+    }
+
+    fn locate(&self, distance: isize, position: &mut Vec<usize>) -> bool {
+        peek_index(self.idx, self.ordered_names.len(), distance)
+            .map(|at| position.push(at))
+            .is_some()
+    }
+
+    fn peek(&mut self, buf: &mut Vec<u8>, distance: isize) -> Result<bool, Error> {
+        let Some(at) = peek_index(self.idx, self.ordered_names.len(), distance) else {
+            return Ok(false);
+        };
+        let idx = std::mem::replace(&mut self.idx, at);
+        let res = self.read_by_index(buf);
+        self.idx = idx;
+        res.map(|_| true)
+    }
+
+    fn sort(&mut self, sort: SortMode) {
+        self.ordered_names
+            .sort_by(|a, b| sort.cmp_name(&a.name, &b.name));
+        self.chapters = group_chapters(&mut self.ordered_names, |entry| &entry.name);
+        self.idx = 0;
+    }
+
+    fn chapters(&self) -> &[(Box<str>, usize)] {
+        &self.chapters
+    }
+
+    fn chapter(&self) -> Option<usize> {
+        chapter_of(&self.chapters, self.idx)
+    }
+}
@@ -0,0 +1,206 @@
+use std::io::{Cursor, Read, Seek};
+
+use tiff::{
+    ColorType,
+    decoder::{Decoder, DecodingResult, Limits},
+    tags::Tag,
+};
+
+use crate::{error::Error, limit, sort::SortMode};
+
+use super::{
+    Direction, File, peek_index,
+    raster::{Samples, cmyk_to_rgb},
+};
+
+// tiff of a single page is a page of the book it's in. more pages make it a book of it's own.
+pub(super) fn is_multi_page<R>(reader: R) -> bool
+where
+    R: Read + Seek,
+{
+    TiffFile::try_new(reader).is_ok_and(|file| file.pages.len() > 1)
+}
+
+// decode the first page of tiff held in bytes into an image render_image can decode.
+pub(super) fn decode_page(bytes: &[u8], buf: &mut Vec<u8>) -> Result<(), Error> {
+    TiffFile::try_new(Cursor::new(bytes))?.read_by_index(buf)
+}
+
+// multi-page tiff where every image directory is a page. reduced resolution copies of a page
+// (thumbnails written next to it) are skipped.
+pub(super) struct TiffFile<R>
+where
+    R: Read + Seek,
+{
+    idx: usize,
+    // index of image directory of every page.
+    pages: Box<[usize]>,
+    decoder: Decoder<R>,
+}
+
+impl<R> TiffFile<R>
+where
+    R: Read + Seek,
+{
+    pub(super) fn try_new(reader: R) -> Result<Self, Error> {
+        let mut limits = Limits::default();
+        let max_alloc = usize::try_from(limit::limits().max_alloc).unwrap_or(usize::MAX);
+        limits.decoding_buffer_size = max_alloc;
+        limits.intermediate_buffer_size = max_alloc;
+
+        let mut decoder = Decoder::new(reader)?.with_limits(limits);
+
+        let mut pages = Vec::new();
+        let mut reduced = Vec::new();
+        for ifd in 0.. {
+            let subfile = decoder
+                .find_tag_unsigned::<u32>(Tag::NewSubfileType)
+                .ok()
+                .flatten()
+                .unwrap_or(0);
+            if subfile & 1 == 0 {
+                pages.push(ifd);
+            } else {
+                reduced.push(ifd);
+            }
+
+            // a broken directory chain ends the document at the last readable page.
+            if !decoder.more_images() || decoder.next_image().is_err() {
+                break;
+            }
+        }
+
+        // tiff made of reduced images only still has something to show.
+        if pages.is_empty() {
+            pages = reduced;
+        }
+
+        Ok(Self {
+            idx: 0,
+            pages: pages.into(),
+            decoder,
+        })
+    }
+
+    fn read_by_index(&mut self, buf: &mut Vec<u8>) -> Result<(), Error> {
+        let Some(&ifd) = self.pages.get(self.idx) else {
+            return Ok(());
+        };
+
+        let decoder = &mut self.decoder;
+        decoder.seek_to_image(ifd)?;
+
+        let (width, height) = decoder.dimensions()?;
+        // raw samples are decoded here before render_image sees them.
+        limit::limits().check_pixels(width, height)?;
+
+        let color = decoder.colortype()?;
+        let (components, bits) = match color {
+            ColorType::Gray(bits) => (1, bits),
+            ColorType::GrayA(bits) => (2, bits),
+            ColorType::RGB(bits) | ColorType::YCbCr(bits) => (3, bits),
+            ColorType::RGBA(bits) | ColorType::CMYK(bits) => (4, bits),
+            ColorType::CMYKA(bits) => (5, bits),
+            ColorType::Multiband {
+                bit_depth,
+                num_samples,
+            } => (num_samples as usize, bit_depth),
+            color => {
+                return Err(Error::Unsupported(format!(
+                    "tiff color type {color:?} is not supported"
+                )));
+            }
+        };
+
+        // samples of every component as 8 bits values.
+        let samples = match decoder.read_image()? {
+            DecodingResult::U8(data) if matches!(bits, 1 | 2 | 4 | 8) => {
+                let samples = Samples {
+                    data: &data,
+                    width,
+                    height,
+                    bits,
+                };
+                samples.iter(components, true).collect::<Vec<_>>()
+            }
+            DecodingResult::U16(data) => data.iter().map(|v| (v >> 8) as u8).collect(),
+            _ => {
+                return Err(Error::Unsupported(format!(
+                    "tiff image with {bits} bits per sample is not supported"
+                )));
+            }
+        };
+
+        // alpha channel is dropped. page is drawn on an opaque background.
+        let pixels = samples.chunks_exact(components);
+        let (channels, pixels) = match color {
+            ColorType::RGB(_) | ColorType::RGBA(_) => {
+                (3, pixels.flat_map(|px| &px[..3]).copied().collect())
+            }
+            ColorType::CMYK(_) | ColorType::CMYKA(_) => {
+                let cmyk = pixels.flat_map(|px| &px[..4]).copied().collect::<Vec<_>>();
+                (3, cmyk_to_rgb(&cmyk))
+            }
+            ColorType::YCbCr(_) => (
+                3,
+                pixels
+                    .flat_map(|px| crate::image::color::ycc_to_rgb(px[0], px[1], px[2]))
+                    .collect(),
+            ),
+            _ => (1, pixels.map(|px| px[0]).collect()),
+        };
+
+        crate::image::write_pnm(buf, width, height, channels, &pixels);
+
+        Ok(())
+    }
+}
+
+impl<R> File for TiffFile<R>
+where
+    R: Read + Seek,
+{
+    fn is_head(&self) -> bool {
+        self.idx == 0
+    }
+
+    fn is_eof(&self) -> bool {
+        self.idx == self.pages.len().saturating_sub(1)
+    }
+
+    fn read(&mut self, buf: &mut Vec<u8>, direction: Direction) -> Result<(), Error> {
+        match direction {
+            Direction::Next if self.is_eof() => return Ok(()),
+            Direction::Prev if self.is_head() => return Ok(()),
+            Direction::Next => self.idx += 1,
+            Direction::Prev => self.idx -= 1,
+            Direction::First => self.idx = 0,
+            Direction::Last => self.idx = self.pages.len().saturating_sub(1),
+            Direction::Offset(idx) => self.idx = idx.min(self.pages.len().saturating_sub(1)),
+            Direction::Chapter(_) => return Ok(()),
+        }
+
+        self.read_by_index(buf)
+    }
+
+    fn locate(&self, distance: isize, position: &mut Vec<usize>) -> bool {
+        peek_index(self.idx, self.pages.len(), distance)
+            .map(|at| position.push(at))
+            .is_some()
+    }
+
+    fn peek(&mut self, buf: &mut Vec<u8>, distance: isize) -> Result<bool, Error> {
+        let Some(at) = peek_index(self.idx, self.pages.len(), distance) else {
+            return Ok(false);
+        };
+        let idx = std::mem::replace(&mut self.idx, at);
+        let res = self.read_by_index(buf);
+        self.idx = idx;
+        res.map(|_| true)
+    }
+
+    // pages are always in document order.
+    fn sort(&mut self, _: SortMode) {
+        self.idx = 0;
+    }
+}
@@ -0,0 +1,78 @@
+use serde_json::Value;
+
+// metadata pixiv downloaders write next to the frames of an ugoira.
+pub(super) const METADATA_NAME: &str = "animation.json";
+
+// frame file names and their delays in milliseconds. the frame list is found at the root of
+// the json or under any key. ({"frames": ..}, {"ugokuIllustData": {"frames": ..}},
+// {"body": {"frames": ..}} are all in use)
+pub(super) fn parse_frames(json: &[u8]) -> Option<Vec<(String, u32)>> {
+    let value = serde_json::from_slice::<Value>(json).ok()?;
+    find_frames(&value)
+}
+
+fn find_frames(value: &Value) -> Option<Vec<(String, u32)>> {
+    match value {
+        Value::Array(frames) => frames
+            .iter()
+            .map(|frame| {
+                let file = frame.get("file")?.as_str()?;
+                let delay = frame.get("delay")?.as_u64()?;
+                Some((String::from(file), delay.min(u32::MAX as u64) as u32))
+            })
+            .collect::<Option<Vec<_>>>()
+            .filter(|frames| !frames.is_empty()),
+        Value::Object(map) => map
+            .get("frames")
+            .and_then(find_frames)
+            .or_else(|| map.values().find_map(find_frames)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(list: &[(&str, u32)]) -> Option<Vec<(String, u32)>> {
+        Some(list.iter().map(|(f, d)| (f.to_string(), *d)).collect())
+    }
+
+    #[test]
+    fn frames_are_found_at_root_or_under_any_key() {
+        let expected = frames(&[("000000.jpg", 60), ("000001.jpg", 100)]);
+        let list = r#"[{"file": "000000.jpg", "delay": 60}, {"file": "000001.jpg", "delay": 100}]"#;
+        assert_eq!(parse_frames(list.as_bytes()), expected);
+        for json in [
+            format!(r#"{{"frames": {list}}}"#),
+            format!(r#"{{"ugokuIllustData": {{"mime_type": "image/jpeg", "frames": {list}}}}}"#),
+            format!(r#"{{"error": false, "body": {{"src": "x.zip", "frames": {list}}}}}"#),
+        ] {
+            assert_eq!(parse_frames(json.as_bytes()), expected);
+        }
+    }
+
+    #[test]
+    fn huge_delay_is_saturated() {
+        let json = r#"[{"file": "a.png", "delay": 99999999999}]"#;
+        assert_eq!(
+            parse_frames(json.as_bytes()),
+            frames(&[("a.png", u32::MAX)])
+        );
+    }
+
+    #[test]
+    fn malformed_frames_are_no_frames() {
+        for json in [
+            "not json",
+            "[]",
+            r#"{"frames": []}"#,
+            r#"[{"file": "a.png"}]"#,
+            r#"[{"file": "a.png", "delay": -1}]"#,
+            r#"[{"file": 1, "delay": 10}]"#,
+            r#"{"illustId": 1}"#,
+        ] {
+            assert_eq!(parse_frames(json.as_bytes()), None, "{json}");
+        }
+    }
+}
@@ -1,7 +1,22 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod avif;
+pub(crate) mod color;
+#[cfg(target_arch = "wasm32")]
+pub(crate) mod web;
+
+use std::{io::Cursor, sync::Once, time::Duration};
+
 use eframe::egui::ColorImage;
-use image::imageops::FilterType;
+use image::{
+    AnimationDecoder, DynamicImage, ImageDecoder, ImageError, ImageFormat, ImageReader,
+    codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+    error::{ImageFormatHint, UnsupportedError, UnsupportedErrorKind},
+    imageops::FilterType,
+    metadata::Orientation,
+};
+use jxl_oxide::{InitializeResult, JxlImage};
 
-use crate::const_image::*;
+use crate::{const_image::*, error::Error, limit};
 
 #[cfg(not(target_arch = "wasm32"))]
 #[cold]
@@ -27,23 +42,259 @@ fn broken() -> ColorImage {
     ColorImage::from_rgba_unmultiplied(BROKEN_IMAGE_SIZE, BROKEN_IMAGE)
 }
 
-pub(crate) fn render_image(buf: &[u8], base_res: &[u32; 2]) -> ColorImage {
-    image::load_from_memory(buf)
-        .map(|mut image| {
-            let [base_w, base_h] = *base_res;
-            let w = image.width();
-            let h = image.height();
+// wrap raw 8 bits gray(1 channel), rgb(3 channels) or rgba(4 channels) pixels with a pnm header
+// so they can be decoded by render_image like any other image file. pnm has no alpha so rgba is
+// wrapped with it's pam extension.
+pub(crate) fn write_pnm(buf: &mut Vec<u8>, width: u32, height: u32, channels: u8, pixels: &[u8]) {
+    let header = match channels {
+        1 => format!("P5\n{width} {height}\n255\n"),
+        3 => format!("P6\n{width} {height}\n255\n"),
+        _ => format!(
+            "P7\nWIDTH {width}\nHEIGHT {height}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n"
+        ),
+    };
+    buf.extend_from_slice(header.as_bytes());
+    buf.extend_from_slice(pixels);
+}
+
+// avif is decoded by browser in the web build before the page is rendered. (see web::Decoding)
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn is_avif(buf: &[u8]) -> bool {
+    image::guess_format(buf).is_ok_and(|format| format == ImageFormat::Avif)
+}
+
+// decoded page. animated image has every frame with it's delay.
+#[derive(Clone)]
+pub(crate) enum Page {
+    Still(ColorImage),
+    Animated(Vec<(ColorImage, Duration)>),
+}
+
+// jpeg xl starts with either a bare codestream or a container box.
+pub(crate) fn is_jxl(head: &[u8]) -> bool {
+    const CODESTREAM_SIGNATURE: &[u8] = b"\xFF\x0A";
+    const CONTAINER_SIGNATURE: &[u8] = b"\0\0\0\x0CJXL \r\n\x87\n";
+    head.starts_with(CODESTREAM_SIGNATURE) || head.starts_with(CONTAINER_SIGNATURE)
+}
+
+// decoders for formats image crate can not decode by itself are hooked into it once.
+fn register_decoders() {
+    static REGISTER: Once = Once::new();
+    REGISTER.call_once(|| {
+        jxl_oxide::integration::register_image_decoding_hook();
+        #[cfg(not(target_arch = "wasm32"))]
+        avif::register_decoding_hook();
+    });
+}
+
+// image going over the limits or using a feature no decoder supports is an error. other
+// undecodable image is shown as broken.
+pub(crate) fn render_image(buf: &[u8], base_res: &[u32; 2]) -> Result<Page, Error> {
+    register_decoders();
+
+    let limits = limit::limits();
+
+    if is_jxl(buf) {
+        check_jxl(buf)?;
+    }
+
+    let reader = || ImageReader::new(Cursor::new(buf)).with_guessed_format();
+    if let Ok((w, h)) = reader()
+        .map_err(Error::from)
+        .and_then(|r| Ok(r.into_dimensions()?))
+    {
+        limits.check_pixels(w, h)?;
+    }
+
+    let mut reader = reader()?;
+
+    // avif page is decoded by browser before it gets here. avif found elsewhere (animation frame)
+    // can not be decoded in the web build.
+    #[cfg(target_arch = "wasm32")]
+    if reader.format() == Some(ImageFormat::Avif) {
+        return Err(Error::Unsupported(
+            "avif frame can not be decoded in the web build. open it in the desktop app".into(),
+        ));
+    }
+
+    // animation that can not be decoded falls back to it's first frame as a still image.
+    if let Some(format) = reader.format() {
+        match decode_frames(buf, format, &limits, base_res) {
+            Ok(Some(page)) => return Ok(page),
+            Err(e @ Error::LimitExceeded(_)) => return Err(e),
+            _ => {}
+        }
+    }
+
+    reader.limits(limits.image());
+
+    let image = match decode_still(reader, buf, &limits) {
+        Err(e @ ImageError::Limits(_)) => return Err(e.into()),
+        // format is known but a feature of it is not. unknown format is a broken image.
+        Err(ImageError::Unsupported(e)) if e.format_hint() != ImageFormatHint::Unknown => {
+            return Err(ImageError::Unsupported(e).into());
+        }
+        res => res,
+    };
+
+    Ok(Page::Still(
+        image
+            .map(|image| fit(image, base_res))
+            .unwrap_or_else(|_| broken()),
+    ))
+}
+
+// animation stored as separate images. (pixiv ugoira) every frame is decoded like a still page.
+pub(crate) fn render_frames(
+    frames: &[(Vec<u8>, Duration)],
+    base_res: &[u32; 2],
+) -> Result<Page, Error> {
+    let limits = limit::limits();
+
+    let mut images = Vec::with_capacity(frames.len());
+    let mut size = 0;
+    for (buf, delay) in frames {
+        let image = match render_image(buf, base_res)? {
+            Page::Still(image) => image,
+            Page::Animated(mut frames) => frames.swap_remove(0).0,
+        };
+        size += image.as_raw().len() as u64;
+        check_frames_size(size, &limits)?;
+        images.push((image, frame_delay(*delay)));
+    }
+
+    match images.len() {
+        1 => Ok(Page::Still(images.swap_remove(0).0)),
+        _ => Ok(Page::Animated(images)),
+    }
+}
+
+// too short delay is played at the same speed browsers do.
+fn frame_delay(delay: Duration) -> Duration {
+    if delay <= Duration::from_millis(10) {
+        Duration::from_millis(100)
+    } else {
+        delay
+    }
+}
+
+fn check_frames_size(size: u64, limits: &limit::Limits) -> Result<(), Error> {
+    if size > limits.max_alloc {
+        return Err(Error::LimitExceeded(format!(
+            "animation frames take over {} bytes",
+            limits.max_alloc
+        )));
+    }
+    Ok(())
+}
+
+// exif orientation and embedded icc profile are applied so the page shows up right side up in
+// srgb.
+fn decode_still(
+    reader: ImageReader<Cursor<&[u8]>>,
+    buf: &[u8],
+    limits: &limit::Limits,
+) -> Result<DynamicImage, ImageError> {
+    let format = reader.format();
+    let mut decoder = reader.into_decoder()?;
+    limits.image().reserve(decoder.total_bytes())?;
+
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let icc = decoder.icc_profile().ok().flatten();
+
+    let mut image = match icc {
+        Some(icc) if format == Some(ImageFormat::Jpeg) && color::is_cmyk(&icc) => {
+            color::decode_cmyk_jpeg(buf, &icc)?
+        }
+        Some(icc) => color::to_srgb(DynamicImage::from_decoder(decoder)?, &icc),
+        None => DynamicImage::from_decoder(decoder)?,
+    };
+    image.apply_orientation(orientation);
+
+    Ok(image)
+}
 
-            if w > base_w || h > base_h {
-                image = image.resize(base_w, base_h, FilterType::Triangle);
+// decode every frame of gif, apng and animated webp. return None for a still image.
+fn decode_frames(
+    buf: &[u8],
+    format: ImageFormat,
+    limits: &limit::Limits,
+    base_res: &[u32; 2],
+) -> Result<Option<Page>, Error> {
+    let reader = Cursor::new(buf);
+    let frames = match format {
+        ImageFormat::Gif => GifDecoder::new(reader)?.into_frames(),
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(reader)?;
+            if !decoder.is_apng()? {
+                return Ok(None);
             }
+            decoder.apng()?.into_frames()
+        }
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(reader)?;
+            if !decoder.has_animation() {
+                return Ok(None);
+            }
+            decoder.into_frames()
+        }
+        _ => return Ok(None),
+    };
+
+    let mut images = Vec::new();
+    let mut size = 0;
+    for frame in frames {
+        let frame = frame?;
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        let delay = frame_delay(Duration::from_millis((numer / denom.max(1)) as u64));
+
+        let image = fit(DynamicImage::ImageRgba8(frame.into_buffer()), base_res);
+        size += image.as_raw().len() as u64;
+        check_frames_size(size, limits)?;
+        images.push((image, delay));
+    }
+
+    match images.len() {
+        0 => Ok(None),
+        1 => Ok(images.pop().map(|(image, _)| Page::Still(image))),
+        _ => Ok(Some(Page::Animated(images))),
+    }
+}
+
+// jxl decoder only renders the first frame of an animation. it's refused instead of shown as a
+// still image.
+fn check_jxl(buf: &[u8]) -> Result<(), Error> {
+    let mut image = JxlImage::builder().build_uninit();
+    if image.feed_bytes(buf).is_err() {
+        return Ok(());
+    }
+    if let Ok(InitializeResult::Initialized(image)) = image.try_init()
+        && image.image_header().metadata.animation.is_some()
+    {
+        return Err(Error::Image(ImageError::Unsupported(
+            UnsupportedError::from_format_and_kind(
+                ImageFormatHint::Name("JPEG XL".into()),
+                UnsupportedErrorKind::GenericFeature("animation".into()),
+            ),
+        )));
+    }
+    Ok(())
+}
+
+// downscale image to fit base resolution.
+fn fit(mut image: DynamicImage, base_res: &[u32; 2]) -> ColorImage {
+    let [base_w, base_h] = *base_res;
+    let w = image.width();
+    let h = image.height();
+
+    if w > base_w || h > base_h {
+        image = image.resize(base_w, base_h, FilterType::Triangle);
+    }
 
-            let w = image.width() as _;
-            let h = image.height() as _;
+    let w = image.width() as _;
+    let h = image.height() as _;
 
-            let pixels = image.into_rgba8().into_flat_samples();
+    let pixels = image.into_rgba8().into_flat_samples();
 
-            ColorImage::from_rgba_unmultiplied([w, h], pixels.as_slice())
-        })
-        .unwrap_or_else(|_| broken())
+    ColorImage::from_rgba_unmultiplied([w, h], pixels.as_slice())
 }
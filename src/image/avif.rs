@@ -0,0 +1,218 @@
+use avif_parse::AvifData;
+use image::{
+    ColorType, ImageDecoder, ImageError, ImageFormat, ImageResult,
+    error::{DecodingError, ImageFormatHint},
+    hooks::{self, GenericReader},
+};
+use re_rav1d::{
+    Decoder, Error as Av1Error, Picture, PixelLayout, PlanarImageComponent,
+    pixel::{MatrixCoefficients, YUVRange},
+};
+
+// image crate only decodes avif with a c library. a decoding hook for the format replaces it.
+// detection hook runs before the built in one so the hook is what decodes avif.
+pub(super) fn register_decoding_hook() {
+    const SIGNATURES: &[&[u8]] = &[b"\0\0\0\0ftypavif", b"\0\0\0\0ftypavis"];
+    const MASK: &[u8] = &[0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+
+    if hooks::register_decoding_hook(
+        "avif".into(),
+        Box::new(|r| Ok(Box::new(AvifDecoder::try_new(r)?))),
+    ) {
+        for signature in SIGNATURES {
+            hooks::register_format_detection_hook("avif".into(), signature, Some(MASK));
+        }
+    }
+}
+
+// size is read from av1 sequence header when opened. pictures are decoded in read_image so
+// asking the dimensions of an image does not decode it.
+struct AvifDecoder {
+    avif: AvifData,
+    width: u32,
+    height: u32,
+}
+
+impl AvifDecoder {
+    fn try_new(mut reader: GenericReader<'_>) -> ImageResult<Self> {
+        let avif = AvifData::from_reader(&mut reader).map_err(decoding_error)?;
+        let metadata = avif.primary_item_metadata().map_err(decoding_error)?;
+
+        Ok(Self {
+            width: metadata.max_frame_width.get(),
+            height: metadata.max_frame_height.get(),
+            avif,
+        })
+    }
+}
+
+impl ImageDecoder for AvifDecoder {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn color_type(&self) -> ColorType {
+        ColorType::Rgba8
+    }
+
+    fn read_image(self, buf: &mut [u8]) -> ImageResult<()> {
+        let picture = decode_av1(&self.avif.primary_item)?;
+        let alpha = self
+            .avif
+            .alpha_item
+            .as_ref()
+            .map(|item| decode_av1(item))
+            .transpose()?;
+
+        // frame can be smaller than the size sequence header allows. buf is sized by the latter.
+        if (picture.width(), picture.height()) != (self.width, self.height) {
+            return Err(decoding_error(format!(
+                "avif picture is {}x{} while it's sequence header tells {}x{}",
+                picture.width(),
+                picture.height(),
+                self.width,
+                self.height
+            )));
+        }
+
+        let width = self.width as usize;
+        let height = self.height as usize;
+
+        let y = Plane::new(&picture, PlanarImageComponent::Y);
+        let (u, v) = match picture.pixel_layout() {
+            PixelLayout::I400 => (None, None),
+            _ => (
+                Some(Plane::new(&picture, PlanarImageComponent::U)),
+                Some(Plane::new(&picture, PlanarImageComponent::V)),
+            ),
+        };
+        let alpha = alpha
+            .as_ref()
+            .filter(|alpha| alpha.width() as usize == width && alpha.height() as usize == height)
+            .map(|alpha| Plane::new(alpha, PlanarImageComponent::Y));
+
+        let (shift_x, shift_y) = match picture.pixel_layout() {
+            PixelLayout::I420 => (1, 1),
+            PixelLayout::I422 => (1, 0),
+            PixelLayout::I400 | PixelLayout::I444 => (0, 0),
+        };
+        let full_range = picture.color_range() == YUVRange::Full;
+        let matrix = picture.matrix_coefficients();
+
+        for (row, pixels) in buf.chunks_exact_mut(width * 4).take(height).enumerate() {
+            for (col, pixel) in pixels.chunks_exact_mut(4).enumerate() {
+                let (cx, cy) = (col >> shift_x, row >> shift_y);
+                let y = y.sample(col, row);
+                let u = u.as_ref().map_or(0.5, |u| u.sample(cx, cy));
+                let v = v.as_ref().map_or(0.5, |v| v.sample(cx, cy));
+                let a = alpha.as_ref().map_or(1.0, |a| a.sample(col, row));
+
+                let mut rgb = yuv_to_rgb(y, u, v, matrix, full_range);
+                if self.avif.premultiplied_alpha && a > 0.0 {
+                    rgb = rgb.map(|c| c / a);
+                }
+
+                let [r, g, b] = rgb.map(to_u8);
+                pixel.copy_from_slice(&[r, g, b, to_u8(a)]);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_image_boxed(self: Box<Self>, buf: &mut [u8]) -> ImageResult<()> {
+        (*self).read_image(buf)
+    }
+}
+
+fn decode_av1(data: &[u8]) -> ImageResult<Picture> {
+    let mut decoder = Decoder::new().map_err(decoding_error)?;
+    decoder
+        .send_data(data.to_vec(), None, None, None)
+        .map_err(decoding_error)?;
+
+    loop {
+        match decoder.get_picture() {
+            Err(Av1Error::Again) => match decoder.send_pending_data() {
+                Ok(()) | Err(Av1Error::Again) => {}
+                Err(e) => return Err(decoding_error(e)),
+            },
+            res => return res.map_err(decoding_error),
+        }
+    }
+}
+
+fn decoding_error<E>(e: E) -> ImageError
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Exact(ImageFormat::Avif),
+        e,
+    ))
+}
+
+// one plane of decoded picture. samples are 8 bits or 16 bits for higher bit depth.
+struct Plane {
+    data: re_rav1d::Plane,
+    stride: usize,
+    wide: bool,
+    max: f32,
+}
+
+impl Plane {
+    fn new(picture: &Picture, component: PlanarImageComponent) -> Self {
+        let depth = picture.bit_depth();
+        Self {
+            data: picture.plane(component),
+            stride: picture.stride(component) as usize,
+            wide: depth > 8,
+            max: ((1 << depth) - 1) as f32,
+        }
+    }
+
+    // sample normalized to 0.0..=1.0
+    fn sample(&self, x: usize, y: usize) -> f32 {
+        let value = if self.wide {
+            let pos = y * self.stride + x * 2;
+            u16::from_ne_bytes([self.data[pos], self.data[pos + 1]]) as f32
+        } else {
+            self.data[y * self.stride + x] as f32
+        };
+        value / self.max
+    }
+}
+
+fn yuv_to_rgb(y: f32, u: f32, v: f32, matrix: MatrixCoefficients, full_range: bool) -> [f32; 3] {
+    let (y, u, v) = if full_range {
+        (y, u - 0.5, v - 0.5)
+    } else {
+        (
+            (y - 16.0 / 255.0) * 255.0 / 219.0,
+            (u - 128.0 / 255.0) * 255.0 / 224.0,
+            (v - 128.0 / 255.0) * 255.0 / 224.0,
+        )
+    };
+
+    // identity matrix stores gbr as is.
+    let (kr, kb) = match matrix {
+        MatrixCoefficients::Identity => return [v + 0.5, y, u + 0.5],
+        MatrixCoefficients::BT709 => (0.2126, 0.0722),
+        MatrixCoefficients::BT470M => (0.30, 0.11),
+        MatrixCoefficients::ST240M => (0.212, 0.087),
+        MatrixCoefficients::BT2020NonConstantLuminance
+        | MatrixCoefficients::BT2020ConstantLuminance => (0.2627, 0.0593),
+        // bt.601 is what unspecified avif is decoded as by other decoders.
+        _ => (0.299, 0.114),
+    };
+    let kg = 1.0 - kr - kb;
+
+    let r = y + 2.0 * (1.0 - kr) * v;
+    let b = y + 2.0 * (1.0 - kb) * u;
+    let g = (y - kr * r - kb * b) / kg;
+    [r, g, b]
+}
+
+fn to_u8(value: f32) -> u8 {
+    (value * 255.0).round().clamp(0.0, 255.0) as u8
+}
@@ -0,0 +1,128 @@
+use image::{
+    DynamicImage, ImageError, ImageResult, RgbaImage,
+    error::{DecodingError, ImageFormatHint},
+};
+use moxcms::{ColorProfile, DataColorSpace, Layout, TransformOptions};
+use zune_core::{bytestream::ZCursor, colorspace::ColorSpace, options::DecoderOptions};
+use zune_jpeg::JpegDecoder;
+
+pub(super) fn is_cmyk(icc: &[u8]) -> bool {
+    ColorProfile::new_from_slice(icc).is_ok_and(|p| p.color_space == DataColorSpace::Cmyk)
+}
+
+// convert image from color space of it's icc profile to srgb. image with a profile that can not
+// be parsed or transformed is kept as is.
+pub(super) fn to_srgb(image: DynamicImage, icc: &[u8]) -> DynamicImage {
+    let Ok(profile) = ColorProfile::new_from_slice(icc) else {
+        return image;
+    };
+
+    let (src, layout) = match profile.color_space {
+        DataColorSpace::Rgb => (image.to_rgba8().into_raw(), Layout::Rgba),
+        DataColorSpace::Gray => (image.to_luma_alpha8().into_raw(), Layout::GrayAlpha),
+        _ => return image,
+    };
+
+    let Ok(transform) = profile.create_transform_8bit(
+        layout,
+        &ColorProfile::new_srgb(),
+        Layout::Rgba,
+        TransformOptions::default(),
+    ) else {
+        return image;
+    };
+
+    let mut dst = vec![0; image.width() as usize * image.height() as usize * 4];
+    if transform.transform(&src, &mut dst).is_err() {
+        return image;
+    }
+
+    RgbaImage::from_raw(image.width(), image.height(), dst)
+        .map(DynamicImage::ImageRgba8)
+        .unwrap_or(image)
+}
+
+// image crate turns cmyk jpeg into rgb without it's profile and the inks can not be recovered
+// from that. the jpeg is decoded again to cmyk and converted with the profile instead.
+pub(super) fn decode_cmyk_jpeg(buf: &[u8], icc: &[u8]) -> ImageResult<DynamicImage> {
+    let options = DecoderOptions::default()
+        .set_strict_mode(false)
+        .set_max_width(usize::MAX)
+        .set_max_height(usize::MAX);
+
+    let mut decoder = JpegDecoder::new_with_options(ZCursor::new(buf), options);
+    decoder.decode_headers().map_err(decoding_error)?;
+    let ycck = decoder.input_colorspace() == Some(ColorSpace::YCCK);
+    let out = if ycck {
+        ColorSpace::YCCK
+    } else {
+        ColorSpace::CMYK
+    };
+    decoder.set_options(decoder.options().jpeg_set_out_colorspace(out));
+
+    let mut pixels = decoder.decode().map_err(decoding_error)?;
+    let (width, height) = decoder
+        .dimensions()
+        .ok_or_else(|| decoding_error("no dimensions"))?;
+
+    // cmyk jpeg is written inverted by photoshop and is read the same way by every decoder.
+    for pixel in pixels.chunks_exact_mut(4) {
+        if ycck {
+            let [r, g, b] = ycc_to_rgb(pixel[0], pixel[1], pixel[2]);
+            pixel.copy_from_slice(&[r, g, b, 255 - pixel[3]]);
+        } else {
+            pixel.iter_mut().for_each(|v| *v = 255 - *v);
+        }
+    }
+
+    let mut dst = vec![0; pixels.len()];
+    let converted = ColorProfile::new_from_slice(icc)
+        .and_then(|profile| {
+            profile.create_transform_8bit(
+                Layout::Rgba,
+                &ColorProfile::new_srgb(),
+                Layout::Rgba,
+                TransformOptions::default(),
+            )
+        })
+        .and_then(|transform| transform.transform(&pixels, &mut dst));
+
+    // without a usable profile inks are mixed the same way image crate does.
+    if converted.is_err() {
+        for (cmyk, rgba) in pixels.chunks_exact(4).zip(dst.chunks_exact_mut(4)) {
+            let k = 255 - cmyk[3] as u32;
+            for i in 0..3 {
+                rgba[i] = ((255 - cmyk[i] as u32) * k / 255) as u8;
+            }
+        }
+    }
+    // transform treats the fourth channel as ink. the result has no alpha.
+    dst.chunks_exact_mut(4).for_each(|rgba| rgba[3] = 255);
+
+    RgbaImage::from_raw(width as u32, height as u32, dst)
+        .map(DynamicImage::ImageRgba8)
+        .ok_or_else(|| decoding_error("pixels do not match dimensions"))
+}
+
+// jfif ycbcr to rgb. in ycck the rgb is cmy inks.
+pub(crate) fn ycc_to_rgb(y: u8, cb: u8, cr: u8) -> [u8; 3] {
+    let y = y as f32;
+    let cb = cb as f32 - 128.0;
+    let cr = cr as f32 - 128.0;
+    [
+        y + 1.402 * cr,
+        y - 0.344136 * cb - 0.714136 * cr,
+        y + 1.772 * cb,
+    ]
+    .map(|v| v.round().clamp(0.0, 255.0) as u8)
+}
+
+fn decoding_error<E>(e: E) -> ImageError
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Exact(image::ImageFormat::Jpeg),
+        e,
+    ))
+}
@@ -0,0 +1,86 @@
+use std::{cell::RefCell, rc::Rc};
+
+use eframe::{
+    egui::Context,
+    wasm_bindgen::{JsCast as _, JsValue},
+};
+use js_sys::{Array, Uint8Array};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, BlobPropertyBag, ImageBitmap, OffscreenCanvasRenderingContext2d};
+
+use crate::{error::Error, limit};
+
+// there is no av1 decoder building for wasm. browser decodes avif page instead and it's pixels
+// are wrapped with a pam header so the page is rendered like any other image. browser decodes
+// asynchronously so the result is polled by worker until it's there.
+pub(crate) struct Decoding(Rc<RefCell<Option<Decoded>>>);
+
+// pam image of decoded page.
+type Decoded = Result<Vec<u8>, Error>;
+
+impl Decoding {
+    pub(crate) fn start(buf: Vec<u8>, ctx: &Context) -> Self {
+        let slot = Rc::new(RefCell::new(None));
+        let decoded = slot.clone();
+        let ctx = ctx.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let res = decode(&buf).await;
+            *decoded.borrow_mut() = Some(res);
+            ctx.request_repaint();
+        });
+        Self(slot)
+    }
+
+    pub(crate) fn try_take(&self) -> Option<Decoded> {
+        self.0.borrow_mut().take()
+    }
+}
+
+async fn decode(buf: &[u8]) -> Decoded {
+    let parts = Array::of1(&Uint8Array::from(buf));
+    let options = BlobPropertyBag::new();
+    options.set_type("image/avif");
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options).map_err(js_error)?;
+
+    let window = web_sys::window().ok_or_else(|| js_error(JsValue::NULL))?;
+    let bitmap = window
+        .create_image_bitmap_with_blob(&blob)
+        .map_err(js_error)?;
+    let bitmap = JsFuture::from(bitmap)
+        .await
+        .map_err(js_error)?
+        .unchecked_into::<ImageBitmap>();
+
+    let (width, height) = (bitmap.width(), bitmap.height());
+    let res = limit::limits()
+        .check_pixels(width, height)
+        .and_then(|_| pixels(&bitmap));
+    bitmap.close();
+
+    let mut page = Vec::new();
+    super::write_pnm(&mut page, width, height, 4, &res?);
+    Ok(page)
+}
+
+// bitmap is drawn on a canvas of it's size to read it's rgba pixels back.
+fn pixels(bitmap: &ImageBitmap) -> Result<Vec<u8>, Error> {
+    let (width, height) = (bitmap.width(), bitmap.height());
+    let canvas = web_sys::OffscreenCanvas::new(width, height).map_err(js_error)?;
+    let context = canvas
+        .get_context("2d")
+        .map_err(js_error)?
+        .ok_or_else(|| js_error(JsValue::NULL))?
+        .unchecked_into::<OffscreenCanvasRenderingContext2d>();
+    context
+        .draw_image_with_image_bitmap(bitmap, 0.0, 0.0)
+        .map_err(js_error)?;
+    let data = context
+        .get_image_data(0.0, 0.0, width as f64, height as f64)
+        .map_err(js_error)?;
+    Ok(data.data().0)
+}
+
+fn js_error(e: JsValue) -> Error {
+    let reason = e.as_string().unwrap_or_else(|| format!("{e:?}"));
+    Error::Unsupported(format!("browser can not decode avif page: {reason}"))
+}
@@ -1,7 +1,13 @@
+pub mod cache;
+mod cancel;
+mod encoding;
 mod error;
 mod file;
 pub mod image;
+pub mod limit;
+mod sort;
 pub mod ui;
+mod worker;
 
 // generated with build.rs
 mod const_image {
@@ -0,0 +1,127 @@
+use std::{
+    io::Read,
+    sync::{PoisonError, RwLock},
+};
+
+use crate::{cancel, error::Error};
+
+// entries smaller than this are never rejected for their compression ratio. a blank page can
+// compress very well.
+const RATIO_FLOOR: u64 = 1024 * 1024;
+
+// safety limits against hostile or corrupted input. going over one is an error instead of an
+// allocation that can take the process down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    // uncompressed bytes of one archive entry. a compressed tarball counts as one entry.
+    pub max_entry_size: u64,
+    // uncompressed size divided by compressed size of one archive entry. deflate tops out near
+    // 1032:1 so the default only refuses what lzma or bzip2 bombs reach. flat color scans go
+    // past 100:1. entry size limit bounds the memory either way.
+    pub max_compression_ratio: u64,
+    // width times height of one decoded image.
+    pub max_pixels: u64,
+    // bytes image decoder can allocate for one image.
+    pub max_alloc: u64,
+}
+
+impl Limits {
+    const DEFAULT: Self = Self {
+        max_entry_size: 1024 * 1024 * 1024,
+        max_compression_ratio: 1000,
+        max_pixels: 128 * 1024 * 1024,
+        max_alloc: 1024 * 1024 * 1024,
+    };
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+static LIMITS: RwLock<Limits> = RwLock::new(Limits::DEFAULT);
+
+// replace the limits files are opened and decoded with from now on.
+pub fn set_limits(limits: Limits) {
+    *LIMITS.write().unwrap_or_else(PoisonError::into_inner) = limits;
+}
+
+pub(crate) fn limits() -> Limits {
+    *LIMITS.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl Limits {
+    // check sizes of archive entry told by it's header before reading it.
+    pub(crate) fn check_entry(&self, name: &str, size: u64, compressed: u64) -> Result<(), Error> {
+        if size > self.max_entry_size {
+            return Err(Error::LimitExceeded(format!(
+                "{name} is {size} bytes uncompressed. the limit is {} bytes",
+                self.max_entry_size
+            )));
+        }
+        if size > RATIO_FLOOR && size / compressed.max(1) > self.max_compression_ratio {
+            return Err(Error::LimitExceeded(format!(
+                "{name} is compressed {}:1. the limit is {}:1",
+                size / compressed.max(1),
+                self.max_compression_ratio
+            )));
+        }
+        Ok(())
+    }
+
+    // check an entry packed together with others. (solid block, rar that tells no packed size)
+    // it's compressed size is estimated as it's share of the packed bytes.
+    pub(crate) fn check_packed_entry(
+        &self,
+        name: &str,
+        size: u64,
+        unpacked: u64,
+        packed: u64,
+    ) -> Result<(), Error> {
+        let compressed = size as u128 * packed as u128 / unpacked.max(1) as u128;
+        self.check_entry(name, size, compressed as u64)
+    }
+
+    pub(crate) fn check_pixels(&self, width: u32, height: u32) -> Result<(), Error> {
+        let pixels = width as u64 * height as u64;
+        if pixels > self.max_pixels {
+            return Err(Error::LimitExceeded(format!(
+                "image is {width}x{height} pixels. the limit is {} pixels",
+                self.max_pixels
+            )));
+        }
+        Ok(())
+    }
+
+    // read to the end of reader into buf. header of an entry can lie about it's size so the
+    // bytes actually read are counted too. reading stops when it's cancelled.
+    pub(crate) fn read_entry<R>(
+        &self,
+        name: &str,
+        reader: R,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), Error>
+    where
+        R: Read,
+    {
+        let len = buf.len();
+        let read = cancel::Reader(reader)
+            .take(self.max_entry_size + 1)
+            .read_to_end(buf)? as u64;
+        if read > self.max_entry_size {
+            buf.truncate(len);
+            return Err(Error::LimitExceeded(format!(
+                "{name} is over {} bytes uncompressed",
+                self.max_entry_size
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn image(&self) -> image::Limits {
+        let mut limits = image::Limits::default();
+        limits.max_alloc = Some(self.max_alloc);
+        limits
+    }
+}
@@ -0,0 +1,200 @@
+use std::cmp::Ordering;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+// order of archive entries and directory listings.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum SortMode {
+    // digit runs compare by numeric value and letters compare case-insensitive.
+    #[default]
+    Natural,
+    // plain byte order. fallback for names natural order can not make sense of.
+    Byte,
+}
+
+impl SortMode {
+    pub(crate) const ALL: [Self; 2] = [Self::Natural, Self::Byte];
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            Self::Natural => "Natural",
+            Self::Byte => "Byte",
+        }
+    }
+
+    // compare '/' separated names from archives. folder components are compared one by one so
+    // every file inside a folder stay together.
+    pub(crate) fn cmp_name(&self, a: &str, b: &str) -> Ordering {
+        match self {
+            Self::Natural => {
+                natural_cmp_components(a.split('/'), b.split('/')).then_with(|| a.cmp(b))
+            }
+            Self::Byte => a.cmp(b),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn cmp_path(&self, a: &Path, b: &Path) -> Ordering {
+        match self {
+            Self::Natural => natural_cmp_components(
+                a.components().map(|c| c.as_os_str().to_string_lossy()),
+                b.components().map(|c| c.as_os_str().to_string_lossy()),
+            )
+            .then_with(|| a.cmp(b)),
+            Self::Byte => a.cmp(b),
+        }
+    }
+}
+
+fn natural_cmp_components<A, B>(mut a: A, mut b: B) -> Ordering
+where
+    A: Iterator,
+    B: Iterator,
+    A::Item: AsRef<str>,
+    B::Item: AsRef<str>,
+{
+    loop {
+        match (a.next(), b.next()) {
+            (Some(a), Some(b)) => match natural_cmp(a.as_ref(), b.as_ref()) {
+                Ordering::Equal => {}
+                o => return o,
+            },
+            (Some(_), None) => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
+            (None, None) => return Ordering::Equal,
+        }
+    }
+}
+
+// compare two strings in human order. "page2" < "page10", "Page1" == "page1" and full-width
+// characters "ｐ１２" are treated the same as "p12".
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ca), Some(cb)) => match (digit(ca), digit(cb)) {
+                (Some(_), Some(_)) => match cmp_digits(&mut a, &mut b) {
+                    Ordering::Equal => {}
+                    o => return o,
+                },
+                _ => {
+                    a.next();
+                    b.next();
+                    match fold(ca).to_lowercase().cmp(fold(cb).to_lowercase()) {
+                        Ordering::Equal => {}
+                        o => return o,
+                    }
+                }
+            },
+        }
+    }
+}
+
+// consume a digit run from both iterators and compare them by numeric value without parsing
+// them into integer. (a digit run can be longer than any integer type.)
+fn cmp_digits<A, B>(a: &mut std::iter::Peekable<A>, b: &mut std::iter::Peekable<B>) -> Ordering
+where
+    A: Iterator<Item = char>,
+    B: Iterator<Item = char>,
+{
+    let a = take_digits(a);
+    let b = take_digits(b);
+
+    let a_trim = trim_zeros(&a);
+    let b_trim = trim_zeros(&b);
+
+    // "01" and "1" have the same value here. callers break the tie with byte order.
+    a_trim
+        .len()
+        .cmp(&b_trim.len())
+        .then_with(|| a_trim.cmp(b_trim))
+}
+
+fn take_digits<I>(iter: &mut std::iter::Peekable<I>) -> Vec<u8>
+where
+    I: Iterator<Item = char>,
+{
+    let mut digits = Vec::new();
+    while let Some(d) = iter.peek().copied().and_then(digit) {
+        digits.push(d);
+        iter.next();
+    }
+    digits
+}
+
+fn trim_zeros(digits: &[u8]) -> &[u8] {
+    let n = digits.iter().take_while(|d| **d == 0).count();
+    &digits[n..]
+}
+
+// map full-width ascii variants to their ascii counterpart.
+fn fold(c: char) -> char {
+    match c {
+        '！'..='～' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        _ => c,
+    }
+}
+
+fn digit(c: char) -> Option<u8> {
+    match c {
+        '0'..='9' => Some(c as u8 - b'0'),
+        // full-width digits.
+        '０'..='９' => Some((c as u32 - '０' as u32) as u8),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digit_runs_compare_by_value() {
+        assert_eq!(natural_cmp("page2", "page10"), Ordering::Less);
+        assert_eq!(natural_cmp("page10", "page9"), Ordering::Greater);
+        assert_eq!(natural_cmp("1-10", "1-9"), Ordering::Greater);
+        // longer than any integer type.
+        assert_eq!(
+            natural_cmp("99999999999999999999999", "100000000000000000000000"),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn leading_zeros_tie_and_byte_order_breaks_it() {
+        assert_eq!(natural_cmp("01", "1"), Ordering::Equal);
+        assert_eq!(natural_cmp("007", "10"), Ordering::Less);
+        assert_eq!(SortMode::Natural.cmp_name("01", "1"), Ordering::Less);
+    }
+
+    #[test]
+    fn full_width_characters_are_folded() {
+        assert_eq!(natural_cmp("ｐ１２", "p12"), Ordering::Equal);
+        assert_eq!(natural_cmp("２", "10"), Ordering::Less);
+        assert_eq!(natural_cmp("page１０", "page9"), Ordering::Greater);
+    }
+
+    #[test]
+    fn case_is_folded() {
+        assert_eq!(natural_cmp("Page1", "page1"), Ordering::Equal);
+        assert_eq!(natural_cmp("b", "A"), Ordering::Greater);
+        assert_eq!(SortMode::Byte.cmp_name("b", "A"), Ordering::Greater);
+        assert_eq!(SortMode::Byte.cmp_name("a", "B"), Ordering::Greater);
+    }
+
+    #[test]
+    fn folder_components_keep_together() {
+        let mut names = vec!["b/1.png", "a10/1.png", "a2/10.png", "a2/9.png", "a.png"];
+        names.sort_by(|a, b| SortMode::Natural.cmp_name(a, b));
+        assert_eq!(
+            names,
+            ["a.png", "a2/9.png", "a2/10.png", "a10/1.png", "b/1.png"]
+        );
+    }
+}
@@ -1,20 +1,50 @@
 use eframe::{
+    App, Frame,
     egui::{
-        load::SizedTexture, Align, Align2, CentralPanel, ColorImage, Context, Key,
-        KeyboardShortcut, Layout, Modifiers, Spinner, TextureHandle, TextureOptions,
-        TopBottomPanel, Ui, Widget, Window,
+        Align, Align2, CentralPanel, ComboBox, Context, DragValue, Key, KeyboardShortcut, Layout,
+        Modifiers, Spinner, TextEdit, TextureHandle, TextureOptions, TopBottomPanel, Ui, Widget,
+        Window, load::SizedTexture,
     },
-    App, Frame,
 };
 
+use std::{borrow::Cow, time::Duration};
+
 #[cfg(target_arch = "wasm32")]
 use std::{cell::RefCell, rc::Rc};
 
-use crate::{error::Error, file::FileObj};
+use crate::{
+    cache,
+    encoding::NameEncoding,
+    error::Error,
+    image::Page,
+    limit,
+    sort::SortMode,
+    worker::{Book, Request, Response, Worker},
+};
+
+// page stays on screen this long while the next one loads before spinner replaces it. quick
+// page turns do not flash the spinner. wasm decodes on ui thread and shows it before blocking.
+#[cfg(not(target_arch = "wasm32"))]
+const LOADING_DELAY: f64 = 0.15;
+#[cfg(target_arch = "wasm32")]
+const LOADING_DELAY: f64 = 0.0;
+
+const MIB: u64 = 1024 * 1024;
 
 pub struct UiObj {
-    file: FileObj,
+    worker: Worker,
+    // state of opened file as of the last answered request.
+    book: Book,
+    // request waiting for it's page.
+    loading: Option<Loading>,
     show_navi: bool,
+    // short message shown in the top bar until next page is displayed.
+    notice: Option<Cow<'static, str>>,
+    // input of password prompt.
+    password: String,
+    remember_password: bool,
+    // frames of current page when it's animated.
+    animation: Option<Animation>,
     #[cfg(not(target_arch = "wasm32"))]
     state: State,
     #[cfg(target_arch = "wasm32")]
@@ -27,6 +57,8 @@ enum State {
     Buf(Vec<u8>),
     Show(TextureHandle),
     ShowError(Error),
+    // encrypted page needs a password. true when the last given one was wrong.
+    Password(bool),
 }
 
 impl State {
@@ -59,6 +91,51 @@ impl StateWasm {
     }
 }
 
+// request sent to worker and not answered yet.
+struct Loading {
+    id: u64,
+    // time of context when it's sent.
+    since: f64,
+    turn: Turn,
+}
+
+// how the page is moved by a request. it decides what is noticed when the page is loaded.
+#[derive(Clone, Copy)]
+enum Turn {
+    Next,
+    Previous,
+    Chapter,
+    Other,
+}
+
+// animated page is played in loop until paused.
+struct Animation {
+    frames: Vec<(TextureHandle, Duration)>,
+    idx: usize,
+    // time of context when the next frame is due.
+    deadline: f64,
+    paused: bool,
+}
+
+impl Animation {
+    fn texture(&self) -> TextureHandle {
+        self.frames[self.idx].0.clone()
+    }
+
+    fn delay(&self) -> f64 {
+        self.frames[self.idx].1.as_secs_f64()
+    }
+
+    fn step(&mut self, forward: bool) {
+        let len = self.frames.len();
+        self.idx = if forward {
+            (self.idx + 1) % len
+        } else {
+            (self.idx + len - 1) % len
+        };
+    }
+}
+
 #[cold]
 #[inline(never)]
 fn default_image_texture(ctx: &Context) -> TextureHandle {
@@ -75,8 +152,14 @@ impl UiObj {
     pub fn new(ctx: &Context, res: [u32; 2]) -> Self {
         let state = State::Show(default_image_texture(ctx));
         Self {
-            file: FileObj::new(res),
+            worker: Worker::new(ctx, res),
+            book: Book::default(),
+            loading: None,
             show_navi: false,
+            notice: None,
+            password: String::new(),
+            remember_password: false,
+            animation: None,
             #[cfg(not(target_arch = "wasm32"))]
             state,
             #[cfg(target_arch = "wasm32")]
@@ -87,145 +170,331 @@ impl UiObj {
     #[cold]
     #[inline(never)]
     fn set_error(&mut self, error: Error) {
-        self.state.set(State::ShowError(error));
+        let state = match error {
+            Error::PasswordRequired => State::Password(false),
+            Error::WrongPassword => State::Password(true),
+            error => State::ShowError(error),
+        };
+        self.animation = None;
+        self.state.set(state);
+    }
+
+    fn set_image(&mut self, page: Page, ctx: &Context) {
+        self.notice = self.book.notice.clone().map(Cow::from);
+        self.animation = None;
+
+        let texture = match page {
+            Page::Still(image) => ctx.load_texture("current-image", image, TextureOptions::LINEAR),
+            Page::Animated(frames) => {
+                let frames = frames
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, (image, delay))| {
+                        let name = format!("current-image-{idx}");
+                        (ctx.load_texture(name, image, TextureOptions::LINEAR), delay)
+                    })
+                    .collect::<Vec<_>>();
+                let animation = Animation {
+                    frames,
+                    idx: 0,
+                    deadline: 0.0,
+                    paused: false,
+                };
+                let texture = animation.texture();
+                self.animation = Some(animation);
+                self.restart_animation(ctx);
+                texture
+            }
+        };
+
+        self.state.set(State::Show(texture));
+    }
+
+    fn restart_animation(&mut self, ctx: &Context) {
+        if let Some(animation) = self.animation.as_mut() {
+            animation.deadline = ctx.input(|s| s.time) + animation.delay();
+            ctx.request_repaint_after(Duration::from_secs_f64(animation.delay()));
+        }
     }
 
-    fn set_image(&mut self, image: ColorImage, ctx: &Context) {
-        self.state.set(State::Show(ctx.load_texture(
-            "current-image",
-            image,
-            TextureOptions::LINEAR,
-        )));
+    // show the next frame of animated page when the delay of current one is over.
+    fn tick_animation(&mut self, ctx: &Context) {
+        let Some(animation) = self.animation.as_mut() else {
+            return;
+        };
+        if animation.paused || !matches!(*self.state.get_mut(), State::Show(_)) {
+            return;
+        }
+
+        let now = ctx.input(|s| s.time);
+        if now >= animation.deadline {
+            animation.step(true);
+            animation.deadline = now + animation.delay();
+            self.state.set(State::Show(animation.texture()));
+        }
+        ctx.request_repaint_after(Duration::from_secs_f64(animation.deadline - now));
+    }
+
+    fn toggle_animation(&mut self, ctx: &Context) {
+        if let Some(animation) = self.animation.as_mut() {
+            animation.paused = !animation.paused;
+            if !animation.paused {
+                self.restart_animation(ctx);
+            }
+        }
+    }
+
+    // show the next or previous frame of animated page. stepping pauses the animation.
+    fn step_animation(&mut self, forward: bool) {
+        if let Some(animation) = self.animation.as_mut() {
+            animation.paused = true;
+            animation.step(forward);
+            self.state.set(State::Show(animation.texture()));
+        }
+    }
+
+    fn request(&mut self, request: Request, turn: Turn, ctx: &Context) {
+        let id = self.worker.send(request);
+        let since = ctx.input(|s| s.time);
+        self.loading = Some(Loading { id, since, turn });
+        ctx.request_repaint_after(Duration::from_secs_f64(LOADING_DELAY));
+    }
+
+    // page is waiting for long enough to be replaced by spinner.
+    fn is_loading_slow(&self, ctx: &Context) -> bool {
+        self.loading
+            .as_ref()
+            .is_some_and(|l| ctx.input(|s| s.time) - l.since >= LOADING_DELAY)
+    }
+
+    fn recv_page(&mut self, ctx: &Context) {
+        let Some(Response { id, page, book }) = self.worker.try_recv() else {
+            return;
+        };
+        // answer to a request that is turned past. the newer one is still loading.
+        let Some(Loading { turn, .. }) = self.loading.take_if(|l| l.id == id) else {
+            return;
+        };
+
+        let chapter = std::mem::replace(&mut self.book, book).chapter;
+        match page {
+            Ok(Some(image)) => {
+                self.set_image(image, ctx);
+                match turn {
+                    Turn::Next | Turn::Previous => self.notice_chapter(chapter),
+                    Turn::Chapter => self.notice_chapter(None),
+                    Turn::Other => {}
+                }
+            }
+            Ok(None) => {
+                match turn {
+                    Turn::Next if self.book.eof => self.notice = Some("end of series".into()),
+                    Turn::Previous if self.book.head => {
+                        self.notice = Some("start of series".into())
+                    }
+                    _ => {}
+                }
+                if matches!(*self.state.get_mut(), State::Loading) {
+                    self.state.set(State::Show(default_image_texture(ctx)));
+                }
+            }
+            Err(e) => self.set_error(e),
+        }
     }
 
     #[cfg(not(target_arch = "wasm32"))]
-    fn try_open(&mut self, path: std::path::PathBuf, ctx: &Context) -> Result<(), Error> {
+    fn open(&mut self, path: std::path::PathBuf, ctx: &Context) {
         self.state.set(State::Loading);
-        if let Some(image) = self.file.try_first(path)? {
-            self.set_image(image, ctx);
-        }
-        Ok(())
+        self.request(Request::Open(path), Turn::Other, ctx);
     }
 
     #[cfg(target_arch = "wasm32")]
-    fn try_open(&mut self, buf: impl AsRef<[u8]> + 'static, ctx: &Context) -> Result<(), Error> {
+    fn open(&mut self, buf: Vec<u8>, ctx: &Context) {
         self.state.set(State::Loading);
-        if let Some(image) = self.file.try_first(buf)? {
-            self.set_image(image, ctx);
-        }
-        Ok(())
+        self.request(Request::Open(buf), Turn::Other, ctx);
     }
 
-    fn try_next(&mut self, ctx: &Context) -> Result<(), Error> {
-        if let Some(image) = self.file.try_next()? {
-            self.set_image(image, ctx);
-        }
-        Ok(())
+    fn next_page(&mut self, ctx: &Context) {
+        self.request(Request::Next, Turn::Next, ctx);
+    }
+
+    fn previous_page(&mut self, ctx: &Context) {
+        self.request(Request::Previous, Turn::Previous, ctx);
     }
 
-    fn try_previous(&mut self, ctx: &Context) -> Result<(), Error> {
-        if let Some(image) = self.file.try_previous()? {
-            self.set_image(image, ctx);
+    // tell where a new chapter begins when page crossed chapter boundary.
+    fn notice_chapter(&mut self, before: Option<usize>) {
+        let chapter = self.book.chapter;
+        if chapter != before
+            && let Some((name, _)) = chapter.and_then(|c| self.book.chapters.get(c))
+        {
+            self.notice = Some(format!("📑 {name}").into());
         }
-        Ok(())
     }
 
-    fn try_rewind(&mut self, ctx: &Context) -> Result<(), Error> {
-        if let Some(image) = self.file.try_rewind()? {
-            self.set_image(image, ctx);
+    fn open_chapter(&mut self, chapter: usize, ctx: &Context) {
+        self.request(Request::Chapter(chapter), Turn::Chapter, ctx);
+    }
+
+    fn next_chapter(&mut self, ctx: &Context) {
+        let next = self.book.chapter.map_or(0, |c| c + 1);
+        if next < self.book.chapters.len() {
+            self.open_chapter(next, ctx);
+        } else if !self.book.chapters.is_empty() {
+            self.notice = Some("last chapter".into());
         }
-        Ok(())
     }
 
-    fn try_skip(&mut self, ctx: &Context) -> Result<(), Error> {
-        if let Some(image) = self.file.try_skip()? {
-            self.set_image(image, ctx);
+    fn previous_chapter(&mut self, ctx: &Context) {
+        match self.book.chapter {
+            Some(0) => self.notice = Some("first chapter".into()),
+            Some(c) => self.open_chapter(c - 1, ctx),
+            None => {}
         }
-        Ok(())
     }
 
-    fn try_listen_input(&mut self, ctx: &Context) -> Result<(), Error> {
+    fn first_page(&mut self, ctx: &Context) {
+        self.request(Request::Rewind, Turn::Other, ctx);
+    }
+
+    fn last_page(&mut self, ctx: &Context) {
+        self.request(Request::Skip, Turn::Other, ctx);
+    }
+
+    fn sort(&mut self, sort: SortMode, ctx: &Context) {
+        self.book.sort = sort;
+        self.request(Request::Sort(sort), Turn::Other, ctx);
+    }
+
+    fn set_name_encoding(&mut self, encoding: NameEncoding, ctx: &Context) {
+        self.book.name_encoding = Some(encoding);
+        self.request(Request::NameEncoding(encoding), Turn::Other, ctx);
+    }
+
+    fn listen_input(&mut self, ctx: &Context) {
+        // keys typed into password prompt are not page turning.
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+
         const CTRL_W: KeyboardShortcut = KeyboardShortcut::new(Modifiers::CTRL, Key::W);
         const CTRL_S: KeyboardShortcut = KeyboardShortcut::new(Modifiers::CTRL, Key::S);
 
-        let (rewind, skip, scroll, arrow_up, arrow_down) = ctx.input_mut(|s| {
-            (
-                s.consume_shortcut(&CTRL_W),
-                s.consume_shortcut(&CTRL_S),
-                s.smooth_scroll_delta,
-                s.key_pressed(Key::W),
-                s.key_pressed(Key::S),
-            )
-        });
+        let (rewind, skip, scroll, arrow_up, arrow_down, arrow_left, arrow_right, q, e) = ctx
+            .input_mut(|s| {
+                (
+                    s.consume_shortcut(&CTRL_W),
+                    s.consume_shortcut(&CTRL_S),
+                    s.smooth_scroll_delta,
+                    s.key_pressed(Key::W),
+                    s.key_pressed(Key::S),
+                    s.key_pressed(Key::A),
+                    s.key_pressed(Key::D),
+                    s.key_pressed(Key::Q),
+                    s.key_pressed(Key::E),
+                )
+            });
+
+        // left and right follow the reading direction. right to left book turns page forward
+        // with left key.
+        let (arrow_next, arrow_previous) = if self.book.rtl {
+            (arrow_left, arrow_right)
+        } else {
+            (arrow_right, arrow_left)
+        };
+
+        if self.animation.is_some() {
+            let (space, comma, period) = ctx.input(|s| {
+                (
+                    s.key_pressed(Key::Space),
+                    s.key_pressed(Key::Comma),
+                    s.key_pressed(Key::Period),
+                )
+            });
+            if space {
+                self.toggle_animation(ctx);
+            } else if comma || period {
+                self.step_animation(period);
+            }
+        }
 
         if rewind {
-            self.try_rewind(ctx)?;
+            self.first_page(ctx);
         } else if skip {
-            self.try_skip(ctx)?;
-        } else if scroll.y < -10.0 || arrow_down {
-            self.try_next(ctx)?;
-        } else if scroll.y > 10.0 || arrow_up {
-            self.try_previous(ctx)?;
+            self.last_page(ctx);
+        } else if q {
+            self.previous_chapter(ctx);
+        } else if e {
+            self.next_chapter(ctx);
+        } else if scroll.y < -10.0 || arrow_down || arrow_next {
+            self.next_page(ctx);
+        } else if scroll.y > 10.0 || arrow_up || arrow_previous {
+            self.previous_page(ctx);
         }
-
-        Ok(())
     }
 
-    fn try_listen_drop(&mut self, ctx: &Context) -> Result<(), Error> {
+    fn listen_drop(&mut self, ctx: &Context) {
         let file = ctx.input_mut(|s| s.raw.dropped_files.pop());
 
         #[cfg(not(target_arch = "wasm32"))]
         {
             if let Some(path) = file.and_then(|file| file.path) {
-                self.try_open(path, ctx)?;
+                self.open(path, ctx);
             }
         }
 
         #[cfg(target_arch = "wasm32")]
         {
             if let Some(bytes) = file.and_then(|file| file.bytes) {
-                self.try_open(bytes, ctx)?;
+                self.open(bytes.to_vec(), ctx);
             }
         }
-
-        Ok(())
     }
 
-    fn try_update(&mut self, ctx: &Context, _frame: &mut Frame) -> Result<(), Error> {
-        self.try_listen_drop(ctx)?;
-        self.try_listen_input(ctx)?;
+    fn update_ui(&mut self, ctx: &Context) {
+        self.listen_drop(ctx);
+        self.listen_input(ctx);
+        self.recv_page(ctx);
+        self.tick_animation(ctx);
 
         self.render_top_bar(ctx);
 
+        let slow = self.is_loading_slow(ctx);
+
         #[allow(dropping_references)]
-        CentralPanel::default()
-            .show(ctx, |ui| {
-                self.render_navi(ui);
-                #[allow(unused_mut)]
-                let mut state = self.state.get_mut();
-                match *state {
-                    State::ShowError(ref e) => {
-                        let string = format!("{e}");
-                        drop(state);
-                        self.render_error(string, ui)
-                    }
-                    #[cfg(target_arch = "wasm32")]
-                    State::Buf(_) => match std::mem::replace(&mut *state, State::Loading) {
-                        State::Buf(buf) => {
-                            drop(state);
-                            self.try_open(buf, ctx)?
-                        }
-                        _ => unreachable!(),
-                    },
-                    State::Loading => {
+        CentralPanel::default().show(ctx, |ui| {
+            self.render_navi(ui);
+            #[allow(unused_mut)]
+            let mut state = self.state.get_mut();
+            match *state {
+                State::ShowError(ref e) => {
+                    let string = format!("{e}");
+                    drop(state);
+                    self.render_error(string, ui)
+                }
+                #[cfg(target_arch = "wasm32")]
+                State::Buf(_) => match std::mem::replace(&mut *state, State::Loading) {
+                    State::Buf(buf) => {
                         drop(state);
-                        self.render_loading(ui)
+                        self.open(buf, ctx)
                     }
-                    State::Show(ref handle) => Self::render_img(handle, ui),
+                    _ => unreachable!(),
+                },
+                State::Loading => {
+                    drop(state);
+                    self.render_loading(ui)
+                }
+                State::Show(_) if slow => {
+                    drop(state);
+                    self.render_loading(ui)
+                }
+                State::Show(ref handle) => Self::render_img(handle, ui),
+                State::Password(wrong) => {
+                    drop(state);
+                    self.render_password(wrong, ui)
                 }
-                Ok(())
-            })
-            .inner
+            }
+        });
     }
 
     fn render_top_bar(&mut self, ctx: &Context) {
@@ -236,9 +505,7 @@ impl UiObj {
                     #[cfg(not(target_arch = "wasm32"))]
                     {
                         if let Some(path) = rfd::FileDialog::new().pick_file() {
-                            if let Err(e) = self.try_open(path, ui.ctx()) {
-                                self.set_error(e);
-                            }
+                            self.open(path, ui.ctx());
                         }
                     }
 
@@ -263,10 +530,147 @@ impl UiObj {
                 if ui.button("⏩ Navi").clicked() {
                     self.show_navi = !self.show_navi;
                 };
+                ui.menu_button("⚙ Settings", Self::render_settings);
+
+                let current = self.book.sort;
+                let mut sort = current;
+                ComboBox::from_id_salt("sort-mode")
+                    .selected_text(format!("🔤 {}", sort.label()))
+                    .show_ui(ui, |ui| {
+                        for mode in SortMode::ALL {
+                            ui.selectable_value(&mut sort, mode, mode.label());
+                        }
+                    });
+                if sort != current {
+                    self.sort(sort, ui.ctx());
+                }
+
+                // only shown for archive with entry names in legacy encoding.
+                if let Some(current) = self.book.name_encoding {
+                    let mut encoding = current;
+                    ComboBox::from_id_salt("name-encoding")
+                        .selected_text(format!("🗋 {}", encoding.label()))
+                        .show_ui(ui, |ui| {
+                            for e in NameEncoding::ALL {
+                                ui.selectable_value(&mut encoding, e, e.label());
+                            }
+                        });
+                    if encoding != current {
+                        self.set_name_encoding(encoding, ui.ctx());
+                    }
+                }
+
+                let chapters = &self.book.chapters;
+                if !chapters.is_empty() {
+                    let current = self.book.chapter;
+                    let mut chapter = current;
+                    let text = current
+                        .and_then(|c| chapters.get(c))
+                        .map_or("", |(name, _)| name);
+                    ComboBox::from_id_salt("chapter")
+                        .selected_text(format!("📑 {text}"))
+                        .show_ui(ui, |ui| {
+                            for (idx, (name, _)) in chapters.iter().enumerate() {
+                                ui.selectable_value(&mut chapter, Some(idx), &**name);
+                            }
+                        });
+                    if chapter != current
+                        && let Some(chapter) = chapter
+                    {
+                        self.open_chapter(chapter, ui.ctx());
+                    }
+                }
+
+                if let Some(animation) = self.animation.as_ref() {
+                    let icon = if animation.paused { "▶" } else { "⏸" };
+                    let text = format!("{icon} {}/{}", animation.idx + 1, animation.frames.len());
+                    if ui.button(text).clicked() {
+                        self.toggle_animation(ui.ctx());
+                    }
+                }
+
+                if let Some(notice) = self.notice.as_deref() {
+                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        ui.label(notice);
+                    });
+                }
             });
         });
     }
 
+    // limits and cache take effect from the next page read.
+    fn render_settings(ui: &mut Ui) {
+        let current = limit::limits();
+        let mut limits = current;
+        ui.label("limits");
+        let mut entry_size = limits.max_entry_size / MIB;
+        let mut pixels = limits.max_pixels / MIB;
+        let mut alloc = limits.max_alloc / MIB;
+        ui.add(
+            DragValue::new(&mut entry_size)
+                .range(1..=u32::MAX)
+                .prefix("entry size: ")
+                .suffix(" MiB"),
+        );
+        ui.add(
+            DragValue::new(&mut limits.max_compression_ratio)
+                .range(1..=u32::MAX)
+                .prefix("compression ratio: ")
+                .suffix(":1"),
+        );
+        ui.add(
+            DragValue::new(&mut pixels)
+                .range(1..=u32::MAX)
+                .prefix("image size: ")
+                .suffix(" Mpx"),
+        );
+        ui.add(
+            DragValue::new(&mut alloc)
+                .range(1..=u32::MAX)
+                .prefix("image memory: ")
+                .suffix(" MiB"),
+        );
+        // values not in whole MiB are kept until they are changed.
+        if entry_size != current.max_entry_size / MIB {
+            limits.max_entry_size = entry_size * MIB;
+        }
+        if pixels != current.max_pixels / MIB {
+            limits.max_pixels = pixels * MIB;
+        }
+        if alloc != current.max_alloc / MIB {
+            limits.max_alloc = alloc * MIB;
+        }
+        if limits != current {
+            limit::set_limits(limits);
+        }
+
+        ui.separator();
+        let current = cache::config();
+        let mut config = current;
+        ui.label("page cache");
+        let mut max_bytes = config.max_bytes / MIB;
+        // browser decodes on ui thread and never prefetches.
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.add(
+            DragValue::new(&mut config.prefetch)
+                .range(0..=16)
+                .prefix("prefetch: ")
+                .suffix(" pages"),
+        );
+        ui.add(
+            DragValue::new(&mut max_bytes)
+                .range(0..=u32::MAX)
+                .prefix("memory: ")
+                .suffix(" MiB"),
+        );
+        if max_bytes != current.max_bytes / MIB {
+            config.max_bytes = max_bytes * MIB;
+        }
+        if config != current {
+            cache::set_config(config);
+        }
+    }
+
     fn render_img(handle: &TextureHandle, ui: &mut Ui) {
         let window_size = ui.available_size();
         let org_size = handle.size_vec2();
@@ -299,30 +703,31 @@ impl UiObj {
                 .frame(eframe::egui::Frame::popup(ui.style()).multiply_with_opacity(0.3))
                 .anchor(Align2::CENTER_TOP, [0.0, 3.0])
                 .show(ui.ctx(), |ui| {
-                    ui.with_layout(Layout::left_to_right(Align::TOP), |ui| {
-                        if ui.button("⏮").clicked() {
-                            if let Err(e) = self.try_rewind(ui.ctx()) {
-                                self.set_error(e);
-                                ui.ctx().request_repaint();
-                            }
+                    // buttons are laid out in reading direction. the left most button leads
+                    // to the end of a right to left book.
+                    let rtl = self.book.rtl;
+                    let layout = if rtl {
+                        Layout::right_to_left(Align::TOP)
+                    } else {
+                        Layout::left_to_right(Align::TOP)
+                    };
+                    ui.with_layout(layout, |ui| {
+                        let (first, previous, next, last) = if rtl {
+                            ("⏭", "▶", "◀", "⏮")
+                        } else {
+                            ("⏮", "◀", "▶", "⏭")
+                        };
+                        if ui.button(first).clicked() {
+                            self.first_page(ui.ctx());
                         }
-                        if ui.button("◀").clicked() {
-                            if let Err(e) = self.try_previous(ui.ctx()) {
-                                self.set_error(e);
-                                ui.ctx().request_repaint();
-                            }
+                        if ui.button(previous).clicked() {
+                            self.previous_page(ui.ctx());
                         }
-                        if ui.button("▶").clicked() {
-                            if let Err(e) = self.try_next(ui.ctx()) {
-                                self.set_error(e);
-                                ui.ctx().request_repaint();
-                            }
+                        if ui.button(next).clicked() {
+                            self.next_page(ui.ctx());
                         }
-                        if ui.button("⏭").clicked() {
-                            if let Err(e) = self.try_skip(ui.ctx()) {
-                                self.set_error(e);
-                                ui.ctx().request_repaint();
-                            }
+                        if ui.button(last).clicked() {
+                            self.last_page(ui.ctx());
                         }
                     })
                 });
@@ -346,12 +751,46 @@ impl UiObj {
                 });
             });
     }
+
+    #[cold]
+    #[inline(never)]
+    fn render_password(&mut self, wrong: bool, ui: &Ui) {
+        Window::new("Password required")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ui.ctx(), |ui| {
+                ui.with_layout(Layout::top_down(Align::Center), |ui| {
+                    if wrong {
+                        ui.label("wrong password. try again");
+                    }
+                    let input = ui.add(TextEdit::singleline(&mut self.password).password(true));
+                    input.request_focus();
+                    // password is remembered by the path of file. browser does not tell it.
+                    #[cfg(not(target_arch = "wasm32"))]
+                    ui.checkbox(&mut self.remember_password, "remember for this session");
+
+                    let enter = input.lost_focus() && ui.input(|s| s.key_pressed(Key::Enter));
+                    ui.horizontal(|ui| {
+                        if ui.button("Confirm").clicked() || enter {
+                            let password = std::mem::take(&mut self.password).into_bytes();
+                            let request = Request::Password(password, self.remember_password);
+                            self.state.set(State::Loading);
+                            self.request(request, Turn::Other, ui.ctx());
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.password.clear();
+                            self.state.set(State::Show(default_image_texture(ui.ctx())));
+                            ui.ctx().request_repaint();
+                        }
+                    });
+                });
+            });
+    }
 }
 
 impl App for UiObj {
-    fn update(&mut self, ctx: &Context, frame: &mut Frame) {
-        if let Err(e) = self.try_update(ctx, frame) {
-            self.set_error(e);
-        }
+    fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
+        self.update_ui(ctx);
     }
 }
@@ -0,0 +1,345 @@
+use std::collections::VecDeque;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, Sender},
+};
+
+use eframe::egui::Context;
+
+#[cfg(target_arch = "wasm32")]
+use crate::image::{self, web::Decoding};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{cache, cancel};
+use crate::{
+    cache::PageCache,
+    encoding::NameEncoding,
+    error::Error,
+    file::{FileObj, RawPage},
+    image::Page,
+    sort::SortMode,
+};
+
+// page loading asked by ui. every request moves the book to another page.
+pub(crate) enum Request {
+    #[cfg(not(target_arch = "wasm32"))]
+    Open(PathBuf),
+    #[cfg(target_arch = "wasm32")]
+    Open(Vec<u8>),
+    Next,
+    Previous,
+    Rewind,
+    Skip,
+    Chapter(usize),
+    Sort(SortMode),
+    NameEncoding(NameEncoding),
+    // password and if it's remembered for the session.
+    Password(Vec<u8>, bool),
+}
+
+pub(crate) struct Response {
+    // id of the request answered.
+    pub(crate) id: u64,
+    pub(crate) page: Result<Option<Page>, Error>,
+    pub(crate) book: Book,
+}
+
+// state of opened file shown by ui. it's taken after every answered request.
+#[derive(Default)]
+pub(crate) struct Book {
+    pub(crate) sort: SortMode,
+    pub(crate) name_encoding: Option<NameEncoding>,
+    pub(crate) chapters: Box<[(Box<str>, usize)]>,
+    pub(crate) chapter: Option<usize>,
+    pub(crate) rtl: bool,
+    pub(crate) head: bool,
+    pub(crate) eof: bool,
+    pub(crate) notice: Option<String>,
+}
+
+impl Book {
+    fn new(file: &FileObj) -> Self {
+        Self {
+            sort: file.sort_mode(),
+            name_encoding: file.name_encoding(),
+            chapters: file.chapters().into(),
+            chapter: file.chapter(),
+            rtl: file.is_rtl(),
+            head: file.is_head(),
+            eof: file.is_eof(),
+            notice: file.notice(),
+        }
+    }
+}
+
+// FileObj with decoded pages cached around the shown one.
+struct Loader {
+    file: FileObj,
+    cache: PageCache,
+    // direction of the last page turn. pages ahead in it are prefetched first.
+    forward: bool,
+}
+
+impl Loader {
+    fn new(res: [u32; 2]) -> Self {
+        Self {
+            file: FileObj::new(res),
+            cache: PageCache::default(),
+            forward: true,
+        }
+    }
+
+    fn run(&mut self, request: Request) -> Result<Option<RawPage>, Error> {
+        let file = &mut self.file;
+        match request {
+            Request::Open(path) => {
+                // pages of another book are never shown again.
+                self.cache.clear();
+                self.forward = true;
+                file.try_first(path)
+            }
+            Request::Next => {
+                self.forward = true;
+                file.try_next()
+            }
+            Request::Previous => {
+                self.forward = false;
+                file.try_previous()
+            }
+            Request::Rewind => file.try_rewind(),
+            Request::Skip => file.try_skip(),
+            Request::Chapter(chapter) => file.try_chapter(chapter),
+            Request::Sort(sort) => file.try_sort(sort),
+            Request::NameEncoding(encoding) => file.try_set_name_encoding(encoding),
+            Request::Password(password, remember) => file.try_password(&password, remember),
+        }
+    }
+
+    // run the first queued request. it's page is only returned when no request is queued after
+    // it. pages turned past are skipped.
+    fn run_queue(
+        &mut self,
+        queue: &mut VecDeque<(u64, Request)>,
+    ) -> Option<(u64, Result<Option<RawPage>, Error>)> {
+        let (id, request) = queue.pop_front()?;
+        let res = self.run(request);
+        queue.is_empty().then_some((id, res))
+    }
+
+    // decode page read by request with given id and answer it.
+    fn answer(&mut self, id: u64, res: Result<Option<RawPage>, Error>) -> Response {
+        let page = res.and_then(|page| page.map(|page| self.render(page)).transpose());
+        Response {
+            id,
+            page,
+            book: Book::new(&self.file),
+        }
+    }
+
+    // decode page read at current position. it's taken from cache when it's decoded before.
+    fn render(&mut self, page: RawPage) -> Result<Page, Error> {
+        let key = self.file.page_key(0);
+        if let Some(page) = key.and_then(|key| self.cache.get(key)) {
+            return Ok(page);
+        }
+        let page = self.file.render(page)?;
+        if let Some(key) = key {
+            self.cache.insert(key, &page);
+        }
+        Ok(page)
+    }
+
+    // decode pages around the shown one into cache. it stops as soon as interrupted tells a new
+    // request is waiting. pages already cached are not read again.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn prefetch(&mut self, mut interrupted: impl FnMut() -> bool) {
+        let count = cache::config().prefetch as isize;
+        for forward in [self.forward, !self.forward] {
+            for distance in 1..=count {
+                if interrupted() {
+                    return;
+                }
+                let distance = if forward { distance } else { -distance };
+                let Some(key) = self.file.page_key(distance) else {
+                    break;
+                };
+                if self.cache.contains(key) {
+                    continue;
+                }
+                let Some(page) = self.file.peek(distance) else {
+                    break;
+                };
+                if let Ok(page) = self.file.render(page) {
+                    self.cache.insert(key, &page);
+                }
+            }
+        }
+    }
+}
+
+// file is opened and decoded on it's own thread. FileObj is created on the thread as some
+// archives can not be sent between threads.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) struct Worker {
+    id: u64,
+    tx: Sender<(u64, Request)>,
+    rx: Receiver<Response>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Worker {
+    pub(crate) fn new(ctx: &Context, res: [u32; 2]) -> Self {
+        let (tx, requests) = mpsc::channel::<(u64, Request)>();
+        let (responses, rx) = mpsc::channel();
+        let ctx = ctx.clone();
+
+        std::thread::Builder::new()
+            .name(String::from("page-loader"))
+            .spawn(move || {
+                let mut loader = Loader::new(res);
+                let mut queue = VecDeque::new();
+                // thread exits when ui is dropped.
+                while let Ok(request) = requests.recv() {
+                    queue.push_back(request);
+                    while let Some(&(id, _)) = queue.front() {
+                        cancel::running(id, false);
+                        let read = loader.run_queue(&mut queue);
+                        queue.extend(requests.try_iter());
+                        // requests before an open have nothing left to do.
+                        if let Some(pos) = queue
+                            .iter()
+                            .rposition(|(_, request)| matches!(request, Request::Open(_)))
+                        {
+                            queue.drain(..pos);
+                        }
+                        // page of a cancelled request is answered by the open after it.
+                        if let Some((id, res)) = read
+                            && !cancel::is_cancelled()
+                        {
+                            if responses.send(loader.answer(id, res)).is_err() {
+                                return;
+                            }
+                            ctx.request_repaint();
+                        }
+                        // pages around are decoded while there is nothing else to do.
+                        if queue.is_empty() {
+                            cancel::running(id, true);
+                            loader.prefetch(|| {
+                                queue.extend(requests.try_iter());
+                                !queue.is_empty()
+                            });
+                        }
+                    }
+                }
+            })
+            .expect("failed to spawn page loading thread");
+
+        Self { id: 0, tx, rx }
+    }
+
+    // queue request and return it's id.
+    pub(crate) fn send(&mut self, request: Request) -> u64 {
+        self.id += 1;
+        let open = matches!(request, Request::Open(_));
+        // worker thread only exits after ui is gone.
+        let _ = self.tx.send((self.id, request));
+        cancel::sent(self.id, open);
+        self.id
+    }
+
+    pub(crate) fn try_recv(&mut self) -> Option<Response> {
+        self.rx.try_iter().last()
+    }
+}
+
+// browser has no thread to decode on. requests are run on ui thread one frame after they are
+// sent so loading spinner is painted before it blocks. page is read in one frame and decoded in
+// the next so ui gets a frame between the two. avif page is decoded by browser in between and
+// polled every frame until it's done. pages are not prefetched as it would stall the input.
+#[cfg(target_arch = "wasm32")]
+pub(crate) struct Worker {
+    id: u64,
+    ctx: Context,
+    loader: Loader,
+    queue: VecDeque<(u64, Request)>,
+    // request is sent in current frame.
+    fresh: bool,
+    // page read by the last request and waiting to be decoded.
+    read: Option<(u64, Result<Option<RawPage>, Error>)>,
+    // avif page of the last request being decoded by browser.
+    decoding: Option<(u64, Decoding)>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Worker {
+    pub(crate) fn new(ctx: &Context, res: [u32; 2]) -> Self {
+        Self {
+            id: 0,
+            ctx: ctx.clone(),
+            loader: Loader::new(res),
+            queue: VecDeque::new(),
+            fresh: false,
+            read: None,
+            decoding: None,
+        }
+    }
+
+    // queue request and return it's id.
+    pub(crate) fn send(&mut self, request: Request) -> u64 {
+        self.id += 1;
+        self.queue.push_back((self.id, request));
+        self.fresh = true;
+        self.ctx.request_repaint();
+        self.id
+    }
+
+    pub(crate) fn try_recv(&mut self) -> Option<Response> {
+        // page read is dropped without decoding when another request is sent after it.
+        if let Some((id, res)) = self.read.take()
+            && self.queue.is_empty()
+        {
+            match res {
+                Ok(Some(RawPage::Image(buf))) if image::is_avif(&buf) && !self.is_cached() => {
+                    self.decoding = Some((id, Decoding::start(buf, &self.ctx)));
+                }
+                res => return Some(self.loader.answer(id, res)),
+            }
+        }
+        if let Some((id, decoding)) = self.decoding.take()
+            && self.queue.is_empty()
+        {
+            let Some(res) = decoding.try_take() else {
+                self.decoding = Some((id, decoding));
+                return None;
+            };
+            return Some(
+                self.loader
+                    .answer(id, res.map(|buf| Some(RawPage::Image(buf)))),
+            );
+        }
+        if self.queue.is_empty() {
+            return None;
+        }
+        if std::mem::take(&mut self.fresh) {
+            self.ctx.request_repaint();
+            return None;
+        }
+        loop {
+            if let Some(read) = self.loader.run_queue(&mut self.queue) {
+                self.read = Some(read);
+                self.ctx.request_repaint();
+                return None;
+            }
+        }
+    }
+
+    // page decoded before is taken from cache without asking browser again.
+    fn is_cached(&self) -> bool {
+        let loader = &self.loader;
+        loader
+            .file
+            .page_key(0)
+            .is_some_and(|key| loader.cache.contains(key))
+    }
+}